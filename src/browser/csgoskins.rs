@@ -0,0 +1,160 @@
+use std::{sync::Mutex, time::Duration};
+
+use ahash::{HashMap, HashMapExt};
+use reqwest::{header::{self, HeaderMap, HeaderValue}, Client, StatusCode};
+use serde_json::{Map, Value};
+
+use crate::{
+    browser::price_source::PriceSource,
+    dprintln,
+    excel::helpers::ProgressSink,
+    models::{price::{Doppler, PriceType}, web::{Sites, FIREFOX_USER_AGENTS}},
+    parsing::{csgoskins_url, item_csgoskins, item_csgotrader}
+};
+
+pub const CSGOSKINS_BASE_URL: &str = "https://csgoskins.gg";
+
+/// Prices scraped from the item pages on csgoskins.gg.
+///
+/// csgoskins has no bulk price list, so every item is fetched once (waiting `pause_time_ms` between each request)
+/// and the offers of all the markets on the page are kept, so checking the next market doesn't fetch the page again.
+pub struct CsgoskinsSource {
+    client: Client,
+    base_url: String,
+    pause_time_ms: u16,
+    offers: Mutex<HashMap<String, HashMap<Sites, f64>>>,
+    // Pages that failed this run (rate limited etc.), so they aren't requested again for every market
+    failed: Mutex<HashMap<String, String>>,
+}
+
+impl CsgoskinsSource {
+    pub fn new(pause_time_ms: u16) -> Self {
+        Self::with_base_url(CSGOSKINS_BASE_URL, pause_time_ms)
+    }
+
+    /// Same as `new`, but fetches the item pages from `base_url` instead of csgoskins.gg.
+    pub fn with_base_url(base_url: &str, pause_time_ms: u16) -> Self {
+        let mut headers: HeaderMap = HeaderMap::new();
+        let user_agent = FIREFOX_USER_AGENTS[ rand::random_range( 0..FIREFOX_USER_AGENTS.len() )];
+        headers.insert( header::USER_AGENT, HeaderValue::from_static( user_agent ) );
+        headers.insert( header::ACCEPT, HeaderValue::from_static("text/html,application/xhtml+xml,application/json;q=0.9,*/*;q=0.8") );
+        headers.insert( header::ACCEPT_LANGUAGE, HeaderValue::from_static("en-GB,en;q=0.5") );
+
+        CsgoskinsSource {
+            client: Client::builder()
+                .default_headers( headers )
+                .brotli(true)
+                .build()
+                .expect("Build of csgoskins client failed"),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            pause_time_ms,
+            offers: Mutex::new(HashMap::new()),
+            failed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Gets the cheapest offer per market for `item_name`, only fetching the item page if it hasn't been fetched already.
+    async fn get_offers<P>(&self, item_name: &str, progress: &mut P) -> Result<HashMap<Sites, f64>, String>
+    where
+        P: ProgressSink
+    {
+        if let Some(offers) = self.offers.lock().map_err(|_| "csgoskins offers lock poisoned.")?.get(item_name) {
+            return Ok(offers.clone());
+        }
+        if let Some(e) = self.failed.lock().map_err(|_| "csgoskins failed pages lock poisoned.")?.get(item_name) {
+            return Err(e.clone());
+        }
+
+        let offers = self.fetch_offers(item_name, progress).await;
+
+        match &offers {
+            Ok(o) => { self.offers.lock().map_err(|_| "csgoskins offers lock poisoned.")?.insert(item_name.to_string(), o.clone()); },
+            Err(e) => { self.failed.lock().map_err(|_| "csgoskins failed pages lock poisoned.")?.insert(item_name.to_string(), e.clone()); }
+        }
+        offers
+    }
+
+    /// Requests the item page, no page (404) is no offers
+    async fn fetch_offers<P>(&self, item_name: &str, progress: &mut P) -> Result<HashMap<Sites, f64>, String>
+    where
+        P: ProgressSink
+    {
+        let url = format!("{}/items/{}", self.base_url, csgoskins_url::create_csgoskins_urls(item_name));
+        dprintln!("Fetching csgoskins offers | {}", url);
+        progress.send_str(&format!("\tFetching csgoskins offers for {}\n", item_name)).await;
+
+        tokio::time::sleep( Duration::from_millis(self.pause_time_ms as u64) ).await;
+
+        let response = self.client.get(&url)
+            .send()
+            .await.map_err(|e| format!("Error sending GET request to csgoskins for {}. \n{}", item_name, e))?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            // Item has no page, so there's nothing to price it with
+            Ok(HashMap::new())
+        } else if !response.status().is_success() {
+            Err( format!("GET Request to csgoskins failed for {}! {}", item_name, response.status()) )
+        } else {
+            let body = response.text()
+                .await.map_err(|e| format!("Unable to read csgoskins response for {} as text. \n{}", item_name, e))?;
+            Ok(item_csgoskins::parse_offers(&body))
+        }
+    }
+}
+
+impl PriceSource for CsgoskinsSource {
+    fn name(&self) -> &'static str {
+        "csgoskins"
+    }
+
+    fn is_per_item(&self) -> bool {
+        true
+    }
+
+    /// Snapshot is a flat `{ item_name: price }` object. Items without an offer on `market` are set to null
+    /// so they're not fetched again until the cache runs out. Items whose page couldn't be fetched are left out.
+    async fn get_market_data<P>(&self, market: Sites, item_names: &[&str], progress: &mut P) -> Result<Value, String>
+    where
+        P: ProgressSink
+    {
+        let mut snapshot: Map<String, Value> = Map::new();
+
+        for name in item_names {
+            // Left out of the snapshot so it's fetched again next time, instead of losing every offer fetched before it
+            let offers = match self.get_offers(name, progress).await {
+                Ok(offers) => offers,
+                Err(e) => {
+                    progress.send_str(&format!("WARNING: Skipped {} on csgoskins. \n{}\n", name, e)).await;
+                    continue
+                }
+            };
+
+            let price = offers
+                .get(&market)
+                .map(|p| Value::from(*p))
+                .unwrap_or(Value::Null);
+
+            snapshot.insert(name.to_string(), price);
+        }
+        Ok(Value::Object(snapshot))
+    }
+
+    async fn get_price<P>(
+        &self,
+        item_name: &str,
+        prices: &Value,
+        market: Sites,
+        want: PriceType,
+        phase: &Option<Doppler>,
+        progress: &mut P
+    ) -> Result<Option<f64>, String>
+    where
+        P: ProgressSink
+    {
+        // The item pages aren't per phase, so every phase would get the price of the base skin
+        if phase.is_some() { return Ok(None) }
+
+        // Flat snapshot is the same shape as the youpin prices from csgotrader
+        item_csgotrader::get_price(item_name, prices, market, want, phase, progress).await
+    }
+}
@@ -3,7 +3,44 @@ use ahash::{HashMap};
 use reqwest::{header::{self, HeaderMap, HeaderValue}, Client};
 use flate2::read::GzDecoder;
 use serde_json::{self, Value};
-use crate::{dprintln, models::web::{Sites, FIREFOX_CSGOTRADERAPP_HEADERS_BASE, FIREFOX_CSGOTRADERAPP_HEADERS_DEFAULT, FIREFOX_USER_AGENTS}};
+use crate::{
+    browser::price_source::PriceSource,
+    dprintln,
+    excel::helpers::ProgressSink,
+    models::{price::{Doppler, PriceType}, web::{Sites, FIREFOX_CSGOTRADERAPP_HEADERS_BASE, FIREFOX_CSGOTRADERAPP_HEADERS_DEFAULT, FIREFOX_USER_AGENTS}},
+    parsing::item_csgotrader
+};
+
+/// Prices from the API used by the csgotrader.app extension.
+pub struct CsgotraderSource;
+
+impl PriceSource for CsgotraderSource {
+    fn name(&self) -> &'static str {
+        "csgotrader"
+    }
+
+    async fn get_market_data<P>(&self, market: Sites, _item_names: &[&str], _progress: &mut P) -> Result<Value, String>
+    where
+        P: ProgressSink
+    {
+        get_market_data(market).await
+    }
+
+    async fn get_price<P>(
+        &self,
+        item_name: &str,
+        prices: &Value,
+        market: Sites,
+        want: PriceType,
+        phase: &Option<Doppler>,
+        progress: &mut P
+    ) -> Result<Option<f64>, String>
+    where
+        P: ProgressSink
+    {
+        item_csgotrader::get_price(item_name, prices, market, want, phase, progress).await
+    }
+}
 
 // USD is 1.0
 pub async fn get_exchange_rates() -> Result<HashMap<String, f64>, String> {
@@ -1,4 +1,6 @@
 pub mod cookies;
 pub mod steamcommunity;
 pub mod csgotrader;
-pub mod csfloat;
\ No newline at end of file
+pub mod csgoskins;
+pub mod csfloat;
+pub mod price_source;
\ No newline at end of file
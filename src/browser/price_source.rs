@@ -0,0 +1,110 @@
+use std::future::Future;
+
+use serde_json::Value;
+
+use crate::{
+    browser::{csgoskins::CsgoskinsSource, csgotrader::CsgotraderSource},
+    excel::helpers::ProgressSink,
+    models::{price::{Doppler, PriceType, PricingProvider}, web::Sites}
+};
+
+/// A site/API that provides the market prices used to evaluate the items.
+///
+/// To add a new pricing provider, implement this trait and add the implementor to `PriceSources`.
+pub trait PriceSource {
+    /// Name of the source, used to tell the cached markets of different sources apart.
+    fn name(&self) -> &'static str;
+
+    /// True if a snapshot only holds prices for the items it was fetched for, instead of the whole market.
+    fn is_per_item(&self) -> bool {
+        false
+    }
+
+    /// Fetches a snapshot of the prices on `market`. `item_names` are the items that need a price,
+    /// which sources that fetch the whole market at once can ignore.
+    fn get_market_data<P>(
+        &self,
+        market: Sites,
+        item_names: &[&str],
+        progress: &mut P
+    ) -> impl Future<Output = Result<Value, String>>
+    where
+        P: ProgressSink;
+
+    /// Looks up the price of `item_name` in a snapshot fetched by `get_market_data`.
+    fn get_price<P>(
+        &self,
+        item_name: &str,
+        prices: &Value,
+        market: Sites,
+        want: PriceType,
+        phase: &Option<Doppler>,
+        progress: &mut P
+    ) -> impl Future<Output = Result<Option<f64>, String>>
+    where
+        P: ProgressSink;
+}
+
+/// Every implemented `PriceSource`, picked from `UserInfo.pricing_provider`.
+pub enum PriceSources {
+    Csgotrader(CsgotraderSource),
+    Csgoskins(CsgoskinsSource),
+}
+
+impl PriceSources {
+    pub fn new(pricing_provider: PricingProvider, pause_time_ms: u16) -> Self {
+        match pricing_provider {
+            PricingProvider::Csgotrader => Self::Csgotrader(CsgotraderSource),
+            PricingProvider::Csgoskins => Self::Csgoskins(CsgoskinsSource::new(pause_time_ms)),
+        }
+    }
+}
+
+impl PriceSource for PriceSources {
+    fn name(&self) -> &'static str {
+        match self {
+            PriceSources::Csgotrader(source) => source.name(),
+            PriceSources::Csgoskins(source) => source.name(),
+        }
+    }
+
+    fn is_per_item(&self) -> bool {
+        match self {
+            PriceSources::Csgotrader(source) => source.is_per_item(),
+            PriceSources::Csgoskins(source) => source.is_per_item(),
+        }
+    }
+
+    async fn get_market_data<P>(
+        &self,
+        market: Sites,
+        item_names: &[&str],
+        progress: &mut P
+    ) -> Result<Value, String>
+    where
+        P: ProgressSink
+    {
+        match self {
+            PriceSources::Csgotrader(source) => source.get_market_data(market, item_names, progress).await,
+            PriceSources::Csgoskins(source) => source.get_market_data(market, item_names, progress).await,
+        }
+    }
+
+    async fn get_price<P>(
+        &self,
+        item_name: &str,
+        prices: &Value,
+        market: Sites,
+        want: PriceType,
+        phase: &Option<Doppler>,
+        progress: &mut P
+    ) -> Result<Option<f64>, String>
+    where
+        P: ProgressSink
+    {
+        match self {
+            PriceSources::Csgotrader(source) => source.get_price(item_name, prices, market, want, phase, progress).await,
+            PriceSources::Csgoskins(source) => source.get_price(item_name, prices, market, want, phase, progress).await,
+        }
+    }
+}
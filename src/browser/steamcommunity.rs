@@ -1,7 +1,14 @@
-use ahash::{HashMap, HashMapExt};
-use reqwest::header::COOKIE;
+use std::{path::PathBuf, time::Duration};
 
-use crate::models::web::{SteamData, SteamJson, GAMES_TRADE_PROTECTED};
+use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+use reqwest::{header::COOKIE, Client};
+
+use chrono::{DateTime, Utc};
+
+use crate::{dprintln, excel::helpers::ProgressSink, models::web::{CachedInventory, SteamData, SteamJson, GAMES_TRADE_PROTECTED}, storage::cache::cache_dir};
+
+const PAGE_SIZE: u16 = 2000;
+const PAGE_PAUSE_MS: u64 = 1500; // Steam rate limits the inventory endpoint hard
 
 struct Description<'a> {
     inspect: Option<&'a str>,
@@ -30,44 +37,64 @@ pub struct SteamInventory {
 }
 impl SteamInventory {
     ///Initializes the connection to the steam inventory and stores the inventory JSON in self
-    pub async fn init(steamid: u64, gameid: u32, cookie: Option<&str>) -> Result<Self, String> {
+    ///
+    ///Follows `last_assetid` until steam says there are no `more_items`, so inventories over `PAGE_SIZE` items aren't cut short.
+    ///The fetched inventory is cached unless it's a dry run.
+    pub async fn init<P>(steamid: u64, gameid: u32, cookie: Option<&str>, dry_run: bool, progress: &mut P) -> Result<Self, String>
+    where
+        P: ProgressSink
+    {
         let client = reqwest::Client::new();
         let cookie = cookie.unwrap_or("");
 
         //                                              https://steamcommunity.com/inventory/76561198389123475/730/2?l=english&count=2000
-        let mut data: SteamJson = client.get(format!("https://steamcommunity.com/inventory/{}/{}/2?l=english&count=2000", steamid, gameid))
-            .header(COOKIE, cookie)
-            .send()
-            .await.map_err( |e| format!("Failed sending main HTTPS request to steam. Check internet connection or steam availability. \n{}", e) )?
-            .json::<SteamJson>()
-            .await.map_err( |e| format!("Failed to parse steam inventory as JSON. This is either because the request is invalid (check that the steamID given is correct), or steam is being silly; try again in like 10sec if so lol.\n{}", e) )?;
+        let mut data: SteamJson = fetch_context(&client, steamid, gameid, 2, cookie, progress).await?;
+        if !dry_run { save_context_cache_or_warn(steamid, 2, &data, progress).await; }
 
         let trade_protected: Option<SteamJson> = if !cookie.is_empty() && GAMES_TRADE_PROTECTED.contains(&gameid) {
-            match client.get(format!("https://steamcommunity.com/inventory/{}/{}/16?l=english&count=2000", steamid, gameid))
-                .header(COOKIE, cookie)
-                .send()
-                .await.map_err( |e| format!("Failed sending trade protect HTTPS request to steam. \n{}", e) ) {
-                    // Fails silently and just returns None since user might not have any trade protected items in inv OR its not their inv
-                    Ok(res) => { res.json::<SteamJson>().await.ok() },
-                    Err(e) => { return Err(e) }
+            // Doesn't stop the program since user might not have any trade protected items in inv OR its not their inv
+            match fetch_context(&client, steamid, gameid, 16, cookie, progress).await {
+                Ok(tp) => {
+                    if !dry_run { save_context_cache_or_warn(steamid, 16, &tp, progress).await; }
+                    Some(tp)
+                },
+                Err(e) => {
+                    dprintln!("Trade protected fetch failed: {}", e);
+                    progress.send_str("WARNING: Couldn't fetch the trade protected items.\n").await;
+                    None
                 }
-        } else { None };
-
-        if let Some(mut tp) = trade_protected {
-            if let Some(ref mut ass) = data.asset_properties && let Some(ref mut more_ass) = tp.asset_properties {
-                ass.append(more_ass);
-            } else if let Some(more_ass) = tp.asset_properties.take() {
-                data.asset_properties = Some(more_ass);
             }
+        } else { None };
 
-            data.assets.append(&mut tp.assets);
-            data.descriptions.append(&mut tp.descriptions);
-            data.total_inventory_count += tp.total_inventory_count;
+        if let Some(tp) = trade_protected {
+            let total = data.total_inventory_count + tp.total_inventory_count;
+            merge_steamjson(&mut data, tp);
+            data.total_inventory_count = total;
         }
 
+        dedupe_descriptions(&mut data.descriptions);
+
         Ok( SteamInventory { data, steamid } )
     }
 
+    ///Loads the inventory that `init` last cached for `steamid`, no matter how old it is. Also returns when it was saved.
+    ///
+    ///Trade protected items (context 16) are only added if they were cached in the same run as the rest of the inventory or later.
+    pub async fn from_cache(steamid: u64) -> Result<(Self, DateTime<Utc>), String> {
+        let cached = load_context_cache(steamid, 2).await?;
+        let mut data = cached.inventory;
+
+        if let Ok(tp) = load_context_cache(steamid, 16).await && tp.timestamp >= cached.timestamp {
+            let total = data.total_inventory_count + tp.inventory.total_inventory_count;
+            merge_steamjson(&mut data, tp.inventory);
+            data.total_inventory_count = total;
+        }
+
+        dedupe_descriptions(&mut data.descriptions);
+
+        Ok( (SteamInventory { data, steamid }, cached.timestamp) )
+    }
+
     ///Gets the names of the items in the inventory aswell as the quantity.
     ///
     ///`marketable` is true if you only want items from inventory that can be traded and/or listed to the community market.
@@ -209,7 +236,10 @@ impl SteamInventory {
                         ),
                         float: data.float,
                         pattern: data.pattern,
-                        asset_id: data.asset_id
+                        asset_id: data.asset_id,
+                        location: None,
+                        phase: None,
+                        manual_note: None
                     }
                 );
             }
@@ -226,7 +256,10 @@ impl SteamInventory {
                         ),
                         float: data.float,
                         pattern: data.pattern,
-                        asset_id: data.asset_id
+                        asset_id: data.asset_id,
+                        location: None,
+                        phase: None,
+                        manual_note: None
                     }
                 );
             }
@@ -243,3 +276,109 @@ impl SteamInventory {
         self.data.total_inventory_count as usize
     }
 }
+
+/// Fetches every page of one inventory context and merges them into one `SteamJson`.
+async fn fetch_context<P>(client: &Client, steamid: u64, gameid: u32, context: u32, cookie: &str, progress: &mut P) -> Result<SteamJson, String>
+where
+    P: ProgressSink
+{
+    let mut data: Option<SteamJson> = None;
+    let mut start_assetid: Option<String> = None;
+    let mut page: u32 = 1;
+
+    loop {
+        let mut url = format!("https://steamcommunity.com/inventory/{}/{}/{}?l=english&count={}", steamid, gameid, context, PAGE_SIZE);
+        if let Some(start) = &start_assetid { url.push_str(&format!("&start_assetid={}", start)); }
+
+        let page_json: SteamJson = client.get(url)
+            .header(COOKIE, cookie)
+            .send()
+            .await.map_err( |e| format!("Failed sending HTTPS request to steam for page {} of context {}. Check internet connection or steam availability. \n{}", page, context, e) )?
+            .json::<SteamJson>()
+            .await.map_err( |e| format!("Failed to parse page {} of steam inventory as JSON. This is either because the request is invalid (check that the steamID given is correct), or steam is being silly; try again in like 10sec if so lol.\n{}", page, e) )?;
+
+        let more_items = page_json.more_items.is_some_and(|m| m != 0);
+        let last_assetid = page_json.last_assetid.clone();
+
+        match &mut data {
+            Some(d) => merge_steamjson(d, page_json),
+            None => data = Some(page_json)
+        }
+
+        // Stops if steam hands back the same cursor so it can't loop forever
+        if !more_items || last_assetid.is_none() || last_assetid == start_assetid { break }
+
+        if let Some(d) = &data {
+            progress.send_str(
+                &format!("Fetched page {} of inventory context {} ({} of {} items)...\n", page, context, d.assets.len(), d.total_inventory_count)
+            ).await;
+        }
+
+        start_assetid = last_assetid;
+        page += 1;
+        tokio::time::sleep(Duration::from_millis(PAGE_PAUSE_MS)).await;
+    }
+
+    data.ok_or_else(|| format!("No pages fetched for inventory context {}.", context))
+}
+
+fn context_cache_path(steamid: u64, context: u32) -> PathBuf {
+    cache_dir().join( format!("steam_inventory_{}_{}.json", steamid, context) )
+}
+
+/// Saves the fetched pages of one context so the inventory can be loaded with `SteamInventory::from_cache`.
+/// Not being able to save it doesn't stop the program.
+async fn save_context_cache_or_warn<P>(steamid: u64, context: u32, data: &SteamJson, progress: &mut P)
+where
+    P: ProgressSink
+{
+    let cache_path = context_cache_path(steamid, context);
+
+    let saved: Result<(), String> = async {
+        let bytes = serde_json::to_vec( &CachedInventory { inventory: data.clone(), timestamp: Utc::now() } )
+            .map_err(|e| format!("Error serializing steam inventory | {}", e))?;
+
+        if let Some(parent_dir) = cache_path.parent() {
+            tokio::fs::create_dir_all(parent_dir).await.map_err(|e| format!("Failed to create cache directories: {}", e))?;
+        }
+        tokio::fs::write(&cache_path, bytes).await.map_err(|e| format!("Error saving steam inventory: {}", e))
+    }.await;
+
+    if let Err(e) = saved {
+        progress.send_str(&format!("WARNING: Failed to cache context {} of the steam inventory. \n{}\n", context, e)).await;
+    }
+}
+
+async fn load_context_cache(steamid: u64, context: u32) -> Result<CachedInventory, String> {
+    let cache_path = context_cache_path(steamid, context);
+
+    let file = tokio::fs::read(&cache_path).await
+        .map_err(|e| format!("No cached inventory found for {} at {}. | {}", steamid, cache_path.display(), e))?;
+
+    serde_json::from_slice::<CachedInventory>(&file)
+        .map_err(|e| format!("Failed to deserialize cached inventory! | {}", e))
+}
+
+/// Appends the assets, descriptions and asset_properties of `other` to `data`. Doesn't touch `total_inventory_count`.
+fn merge_steamjson(data: &mut SteamJson, mut other: SteamJson) {
+    if let Some(ref mut ass) = data.asset_properties && let Some(ref mut more_ass) = other.asset_properties {
+        ass.append(more_ass);
+    } else if let Some(more_ass) = other.asset_properties.take() {
+        data.asset_properties = Some(more_ass);
+    }
+
+    data.assets.append(&mut other.assets);
+    data.descriptions.append(&mut other.descriptions);
+}
+
+/// Pages (and contexts) repeat the descriptions of items they share, so only the first one of every classid/instanceid is kept.
+fn dedupe_descriptions(descriptions: &mut Vec<serde_json::Value>) {
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+
+    descriptions.retain(|desc| {
+        let classid = desc.get("classid").and_then(|v| v.as_str()).unwrap_or_default();
+        let instanceid = desc.get("instanceid").and_then(|v| v.as_str()).unwrap_or_default();
+
+        seen.insert((classid.to_string(), instanceid.to_string()))
+    });
+}
@@ -1,8 +1,12 @@
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use crate::excel::excel_runtime;
-use crate::models::user_sheet::UserSheet;
+use crate::excel::helpers::format_age;
+use crate::storage::backup;
+use crate::storage::cache::{self, CacheKind, PurgeFilter};
+use crate::models::user_sheet::{QuantityDecrease, UserSheet};
 use crate::parsing::load_file;
 use crate::cli::templates_n_methods::{
     args_next_or_error,
@@ -33,8 +37,19 @@ On Linux:
 
 -pts OR -pathtosheet [path]  |  Changes/Provides the spreadsheet that you want to operate on.
 	[spreadsheet_path] has to be a full path (ex: C:\Users\SumYoungGuy\spreadsheet.xlsx).
+	.ods (LibreOffice) files work too, only the cells the program manages are written back so your formulas are kept.
+
+-su OR -storageunits [path]  |  Adds the items in your storage units from a JSON export of their contents.
+	[path] has to be a full path (ex: C:\Users\SumYoungGuy\storage_units.json).
+	Set the location column in the savefile to see which storage unit each item is in.
+
+-mi OR -manualitems [path]  |  Adds the items in a JSON list that aren't in your inventory, priced like the rest.
+	Each item has a "name" (full market name) and optionally "quantity", "phase", "float", "note" and "asset_id".
+	asset_id is needed when not grouping simular items, and can be any number as long as it's unique.
+	Ex: [{ "name": "AK-47 | Redline (Field-Tested)", "quantity": 2, "note": "Listed on csfloat" }]
 
 -id OR -steamid [number]  |  Changes/Provides the steamid of the loaded savefile to the given [number].
+	If the savefile has a list of accounts, those are run instead (each written to its own sheet).
 
 -sls OR -steamloginsecure [text]  |  Changes/Provides the steamLoginSecure of the loaded savefile to the given [text].
 	This is useful if you want the most up-to-date items from your inventory.
@@ -48,7 +63,44 @@ On Linux:
 
 -fp OR -fetchprices [y/n]  |  Do/Don't update the prices in the Spreadsheet.
 
+-off OR -offline  |  Runs without internet using the cached inventory, prices and exchange rates,
+    no matter how old they are. Cache is saved every time the program runs online.
+
+-fr OR -forcerefresh  |  Fetches the prices of every market even if the cached prices are still valid.
+    How long the prices are cached for each market is set with cache_ttl_minutes in the savefile (ex: "STEAM": 60).
+    Fees per market for the net price column and the Best Net pricing mode are set with market_fees (ex: "STEAM": 13.0).
+
+-rc OR -reconcile [y/n]  |  Do/Don't mark the rows of items that aren't in the inventory anymore.
+    Marked in the status column (col_status in the savefile) if set, otherwise the name is struck through.
+
+-qd OR -quantitydecrease [never/always/record]  |  What to do when grouping similar items and you have less of an item than the sheet says.
+    never only raises quantities, always lowers them to match Steam,
+    record also adds how many left to the quantity removed column (col_quantity_removed in the savefile).
+
+-csv OR -exportcsv [y/n]  |  Do/Don't write every row to a csv file next to the spreadsheet after the run.
+
+-jsonl OR -exportjsonl [y/n]  |  Do/Don't write every row to a JSON Lines (.jsonl) file next to the spreadsheet after the run.
+
+-dr OR -dryrun  |  Does everything except writing to the spreadsheet, exports, price history and the cache.
+    Prints every cell that would change (sheet, row, column, old and new value) instead.
+
+-rb OR -restorebackup  |  Copies the newest backup of the spreadsheet over it and exits, without running.
+    A backup is made in the 'backups' folder next to the spreadsheet every time it is written to.
+    How many are kept is set with backup_count in the savefile (default 5, 0 turns backups off).
+
 -np OR -noprint  |  Disables printing progress and warnings to stdout, but errors still get printed.
+
+
+--- CACHE ---
+These don't need a savefile and exit right after.
+
+-cl OR -cachelist  |  Lists every cached market, inventory and exchange rate with its age and size.
+
+-ci OR -cacheinspect [name]  |  Shows the cached price data of the item with the full market name [name] for every cached market.
+	Ex: -ci "AK-47 | Redline (Field-Tested)"
+
+-cp OR -cachepurge [all/market/age]  |  Deletes everything, only the cached prices of a market,
+	or everything older than an age given in hours or days (ex: -cp buff163, -cp 12h, -cp 7d).
 "#;
 
 pub fn init_cli(args: Vec<String>) -> Result<(), String> {
@@ -56,6 +108,7 @@ pub fn init_cli(args: Vec<String>) -> Result<(), String> {
     let mut data: Option<UserSheet> = None;
 
     let mut verbose_cli_out: bool = true;
+    let mut restore_backup: bool = false;
 
     let mut caller_arg: String;
 
@@ -70,6 +123,24 @@ pub fn init_cli(args: Vec<String>) -> Result<(), String> {
                 println!("{}", HELP_MSG);
                 return Ok(());
             },
+            "-cachelist" | "--cachelist" | "-cl" | "--cl" => {
+                print_cache_list()?;
+                return Ok(());
+            },
+            "-cacheinspect" | "--cacheinspect" | "-ci" | "--ci" => {
+                let name = args_next_or_error(&mut args, &caller_arg)?;
+
+                print_cached_item(name)?;
+                return Ok(());
+            },
+            "-cachepurge" | "--cachepurge" | "-cp" | "--cp" => {
+                let filter = PurgeFilter::from_str( args_next_or_error(&mut args, &caller_arg)? )?;
+
+                let purged = cache::purge_cache(filter)?;
+                for path in &purged { println!("Deleted {}", path.display()); }
+                println!("Purged {} file(s) from the cache.", purged.len());
+                return Ok(());
+            },
             "-load" | "--load" | "-l" | "--l"  => {
                 let to_load = args_next_or_error(&mut args, &caller_arg)?;
 
@@ -108,6 +179,28 @@ pub fn init_cli(args: Vec<String>) -> Result<(), String> {
 
                 actions.insert( CliActionQueue::PathToSheet(PathBuf::from(pts)) );
             },
+            "-storageunits" | "--storageunits" | "-su" | "--su" => {
+                let su = args_next_or_error(&mut args, &caller_arg)?;
+
+                let pathbuf = PathBuf::from(su);
+
+                if !pathbuf.is_file() {
+                    return Err( format!("Path {} could not be found.", su) );
+                }
+
+                actions.insert( CliActionQueue::StorageUnits(pathbuf) );
+            },
+            "-manualitems" | "--manualitems" | "-mi" | "--mi" => {
+                let mi = args_next_or_error(&mut args, &caller_arg)?;
+
+                let pathbuf = PathBuf::from(mi);
+
+                if !pathbuf.is_file() {
+                    return Err( format!("Path {} could not be found.", mi) );
+                }
+
+                actions.insert( CliActionQueue::ManualItems(pathbuf) );
+            },
             "-steamid" | "--steamid" | "-id" | "--id" => {
                 let id = args_next_or_error(&mut args, &caller_arg)?;
 
@@ -116,6 +209,18 @@ pub fn init_cli(args: Vec<String>) -> Result<(), String> {
                     Err(_) => { return Err( format!("couldn't read {} as a steamid.", id) ); }
                 }
             },
+            "-offline" | "-off" | "--offline" | "--off" => {
+                actions.insert( CliActionQueue::Offline );
+            },
+            "-dryrun" | "-dr" | "--dryrun" | "--dr" => {
+                actions.insert( CliActionQueue::DryRun );
+            },
+            "-forcerefresh" | "-fr" | "--forcerefresh" | "--fr" => {
+                actions.insert( CliActionQueue::ForceRefresh );
+            },
+            "-restorebackup" | "-rb" | "--restorebackup" | "--rb" => {
+                restore_backup = true;
+            },
             "-noprint" | "-np" | "--noprint" | "--np" => {
                 verbose_cli_out = false;
             },
@@ -139,6 +244,41 @@ pub fn init_cli(args: Vec<String>) -> Result<(), String> {
                     &caller_arg
                 )?;
             },
+            "-qd" | "-quantitydecrease" | "--qd" | "--quantitydecrease" => {
+                let qd = args_next_or_error(&mut args, &caller_arg)?;
+
+                actions.insert( CliActionQueue::QuantityDecrease(QuantityDecrease::from_str(qd)?) );
+            },
+            "-rc" | "-reconcile" | "--rc" | "--reconcile" => {
+                let arg = args_next_or_error(&mut args, &caller_arg)?;
+
+                bool_action_or_error(
+                    &mut actions,
+                    CliActionQueue::Reconcile,
+                    arg,
+                    &caller_arg
+                )?;
+            },
+            "-csv" | "-exportcsv" | "--csv" | "--exportcsv" => {
+                let arg = args_next_or_error(&mut args, &caller_arg)?;
+
+                bool_action_or_error(
+                    &mut actions,
+                    CliActionQueue::ExportCsv,
+                    arg,
+                    &caller_arg
+                )?;
+            },
+            "-jsonl" | "-exportjsonl" | "--jsonl" | "--exportjsonl" => {
+                let arg = args_next_or_error(&mut args, &caller_arg)?;
+
+                bool_action_or_error(
+                    &mut actions,
+                    CliActionQueue::ExportJsonl,
+                    arg,
+                    &caller_arg
+                )?;
+            },
             "-is" | "-ignoresold" | "--is" | "--ignoresold" => {
                 let arg = args_next_or_error(&mut args, &caller_arg)?;
 
@@ -167,6 +307,12 @@ pub fn init_cli(args: Vec<String>) -> Result<(), String> {
             CliActionQueue::SteamId(id) => {
                 data.user.steamid = id;
             },
+            CliActionQueue::StorageUnits(path) => {
+                data.user.storage_units_path = Some(path);
+            },
+            CliActionQueue::ManualItems(path) => {
+                data.user.manual_items_path = Some(path);
+            },
             CliActionQueue::FetchPrices(b) => {
                 data.user.fetch_prices = b;
             },
@@ -175,10 +321,39 @@ pub fn init_cli(args: Vec<String>) -> Result<(), String> {
             }
             CliActionQueue::FetchSteam(b) => {
                 data.user.fetch_steam = b;
+            },
+            CliActionQueue::QuantityDecrease(qd) => {
+                data.user.quantity_decrease = qd;
+            },
+            CliActionQueue::Reconcile(b) => {
+                data.user.reconcile = b;
+            },
+            CliActionQueue::ExportCsv(b) => {
+                data.user.export_csv = b;
+            },
+            CliActionQueue::ExportJsonl(b) => {
+                data.user.export_jsonl = b;
+            },
+            CliActionQueue::Offline => {
+                data.user.offline = true;
+            },
+            CliActionQueue::DryRun => {
+                data.user.dry_run = true;
+            },
+            CliActionQueue::ForceRefresh => {
+                data.user.force_refresh = true;
             }
         }
     }
 
+    if restore_backup {
+        let path = data.sheet.path_to_sheet.ok_or("No spreadsheet to restore. Set the path to the sheet with '-pts [path]' or in the savefile.")?;
+        let restored = backup::restore_last_backup(&path)?;
+
+        println!("Restored {} from {}", path.display(), restored.display());
+        return Ok(())
+    }
+
     let tokio_runtime = tokio::runtime::Runtime::new().map_err(|_| "Failed to start async runtime.")?;
 
     if verbose_cli_out {
@@ -191,3 +366,41 @@ pub fn init_cli(args: Vec<String>) -> Result<(), String> {
         )
     }
 }
+
+fn print_cache_list() -> Result<(), String> {
+    let entries = cache::list_cache()?;
+
+    println!("Cache dir: {}\n", cache::cache_dir().display());
+    if entries.is_empty() { println!("Nothing cached."); }
+
+    for entry in entries {
+        let what = match &entry.kind {
+            CacheKind::Market { market, source } => format!("{} prices from {}", market, source),
+            CacheKind::Inventory { steamid, context } => format!("Inventory of {} (context {})", steamid, context),
+            CacheKind::ExchangeRates => String::from("Exchange rates"),
+            CacheKind::Other => entry.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+        };
+
+        println!(
+            "\t{:-<50} AGE: {:<10} SIZE: {:.1} KB",
+            what,
+            entry.age().map(format_age).unwrap_or(String::from("?")),
+            entry.size as f64 / 1024.0
+        );
+    }
+    Ok(())
+}
+
+fn print_cached_item(name: &str) -> Result<(), String> {
+    let found = cache::inspect_item(name)?;
+
+    if found.is_empty() { println!("No cached markets."); }
+
+    for (market, source, item) in found {
+        match item {
+            Some(item) => println!("{} ({}): {}", market, source, item),
+            None => println!("{} ({}): not cached", market, source)
+        }
+    }
+    Ok(())
+}
@@ -3,6 +3,7 @@ use std::path::PathBuf;
 use std::slice::Iter;
 
 use crate::excel::helpers::{ProgressSink, Progress};
+use crate::models::user_sheet::QuantityDecrease;
 
 pub fn args_next_or_error<'a>(
     args: &mut Iter<'a, String>,
@@ -60,8 +61,17 @@ impl ProgressSink for NoProgressSink {
 pub enum CliActionQueue {
     SteamLoginSecure(String),
     PathToSheet(PathBuf),
+    StorageUnits(PathBuf),
+    ManualItems(PathBuf),
     SteamId(u64),
     FetchPrices(bool),
     FetchSteam(bool),
-    IgnoreSold(bool)
+    IgnoreSold(bool),
+    Reconcile(bool),
+    QuantityDecrease(QuantityDecrease),
+    ExportCsv(bool),
+    ExportJsonl(bool),
+    Offline,
+    DryRun,
+    ForceRefresh
 }
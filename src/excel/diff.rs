@@ -0,0 +1,48 @@
+use umya_spreadsheet::{helper::coordinate::string_from_column_index, Spreadsheet, Worksheet};
+
+/// A cell that would be changed by the run
+#[derive(Debug)]
+pub struct CellChange {
+    pub sheet: String,
+    pub row: u32,
+    pub col: String,
+    pub old: String,
+    pub new: String,
+}
+
+fn cell_value(sheet: Option<&Worksheet>, col: u32, row: u32) -> String {
+    sheet.and_then(|s| s.get_cell((col, row)))
+        .map(|c| c.get_value().to_string())
+        .unwrap_or_default()
+}
+
+/// Every cell that is different in `after` compared to `before`, sheet by sheet in the order of `after`.
+/// Sheets that only exist in `after` have every cell listed as new.
+pub fn diff_books(before: &Spreadsheet, after: &Spreadsheet) -> Vec<CellChange> {
+    let mut changes: Vec<CellChange> = Vec::new();
+
+    for sheet in after.get_sheet_collection() {
+        let old_sheet = before.get_sheet_by_name(sheet.get_name());
+
+        let (new_cols, new_rows) = sheet.get_highest_column_and_row();
+        let (old_cols, old_rows) = old_sheet.map(|s| s.get_highest_column_and_row()).unwrap_or((0, 0));
+
+        for row in 1..=new_rows.max(old_rows) {
+            for col in 1..=new_cols.max(old_cols) {
+                let old = cell_value(old_sheet, col, row);
+                let new = cell_value(Some(sheet), col, row);
+
+                if old != new {
+                    changes.push( CellChange {
+                        sheet: sheet.get_name().to_string(),
+                        row,
+                        col: string_from_column_index(&col),
+                        old,
+                        new
+                    } );
+                }
+            }
+        }
+    }
+    changes
+}
@@ -1,7 +1,7 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}, str::FromStr};
 use umya_spreadsheet::{reader, writer, Spreadsheet, Worksheet, XlsxError};
 
-use crate::{dprintln, excel::helpers::{ProgressSink, ToColumn, generate_fallback_path}, models::{excel::ExcelData, price::Doppler, user_sheet::SheetInfo}};
+use crate::{dprintln, excel::{ods::{is_ods, read_ods_as_book}, helpers::{ProgressSink, ToColumn, generate_fallback_path, insert_number_in_sheet, insert_string_in_sheet}}, models::{excel::{ExcelData, RealizedRow}, price::Doppler, user_sheet::SheetInfo}, storage::exchange_history::excel_serial_to_date};
 
 pub async fn get_spreadsheet<P>(
     path: &mut Option<PathBuf>,
@@ -13,6 +13,8 @@ where
     P: ProgressSink
 {
     if let Some(pts) = path {
+        if is_ods(pts) { return read_ods_as_book(pts) }
+
         let sheet = reader::xlsx::read(pts).map_err(|_| String::from("Failed to read file"))?;
         Ok(sheet)
     } else {
@@ -45,16 +47,229 @@ where
      }
 }
 
-pub async fn set_spreadsheet(path: &Option<PathBuf>, steamid: u64, book: Spreadsheet) -> Result<(), XlsxError> {
-    if let Some(pts) = path { writer::xlsx::write(&book, pts)?}
-    else {
-        let mut path: Option<PathBuf> = None;
-        generate_fallback_path(&mut path, steamid);
-        writer::xlsx::write(&book, path.unwrap())?
+/// Writes the book to `path`, or the fallback path if None. Returns where it was written.
+pub async fn set_spreadsheet(path: &Option<PathBuf>, steamid: u64, book: Spreadsheet) -> Result<PathBuf, String> {
+    let path = match path {
+        Some(pts) => pts.clone(),
+        None => {
+            let mut path: Option<PathBuf> = None;
+            generate_fallback_path(&mut path, steamid);
+            path.unwrap()
+        }
+    };
+
+    write_atomically(&path, |tmp| {
+        writer::xlsx::write(&book, tmp).map_err(|e|
+            if matches!(e, XlsxError::Io(_)) { format!("{} | Close Excel/LibreOffice/OpenOffice if it is open.", e) }
+            else { e.to_string() }
+        )
+    })?;
+    Ok(path)
+}
+
+/// Writes to a temp file next to `target` with `write` and then renames it over `target`,
+/// so a crash in the middle of writing never leaves a half written spreadsheet.
+///
+/// If `target` can't be replaced (usually because it's open in Excel), the new spreadsheet is kept under another name
+/// and the error says where.
+pub fn write_atomically<F>(target: &Path, write: F) -> Result<(), String>
+where
+    F: FnOnce(&Path) -> Result<(), String>
+{
+    let stem = target.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| String::from("spreadsheet"));
+    let extension = target.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+
+    let tmp = target.with_file_name(format!("{}.cs2excel-tmp{}", stem, extension));
+
+    if let Err(e) = write(&tmp) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e)
+    }
+
+    if let Err(e) = fs::rename(&tmp, target) {
+        let alternate = target.with_file_name(
+            format!("{}_new_{}{}", stem, chrono::Local::now().format("%Y%m%d_%H%M%S"), extension)
+        );
+
+        return Err( match fs::rename(&tmp, &alternate) {
+            Ok(()) => format!(
+                "Couldn't replace {} ({}). Close Excel/LibreOffice/OpenOffice if it is open.\nThe new spreadsheet was saved as {} instead.",
+                target.display(), e, alternate.display()
+            ),
+            Err(_) => format!(
+                "Couldn't replace {} ({}). Close Excel/LibreOffice/OpenOffice if it is open.\nThe new spreadsheet was left at {}.",
+                target.display(), e, tmp.display()
+            )
+        })
+    }
+    Ok(())
+}
+
+const HISTORY_HEADERS: [&str; 5] = ["Date", "Total value", "Items", "Currency", "USD to X"];
+
+/// Appends one row (date, total value, item count, currency, rate) to the sheet called `history_sheet_name`,
+/// creating the sheet with a header row if it doesn't exist yet.
+pub fn append_value_history(
+    book: &mut Spreadsheet,
+    history_sheet_name: &str,
+    date: &str,
+    total_value: f64,
+    item_count: u32,
+    currency: &str,
+    rate: f64
+) -> Result<(), String> {
+    let sheet: &mut Worksheet = if book.get_sheet_by_name(history_sheet_name).is_some() {
+        book.get_sheet_by_name_mut(history_sheet_name)
+            .ok_or_else(|| format!("Failed to get the history sheet {}.", history_sheet_name))?
+    } else {
+        book.new_sheet(history_sheet_name)
+            .map_err(|e| format!("Failed to create the history sheet {}. {}", history_sheet_name, e))?
+    };
+
+    if sheet.get_highest_row() == 0 {
+        for (i, header) in HISTORY_HEADERS.iter().enumerate() {
+            sheet.get_cell_mut(((i + 1) as u32, 1)).set_value(*header);
+        }
     }
+
+    let row = sheet.get_highest_row() as usize + 1;
+
+    insert_string_in_sheet(sheet, "A", row, date);
+    insert_number_in_sheet(sheet, "B", row, total_value);
+    insert_number_in_sheet(sheet, "C", row, item_count);
+    insert_string_in_sheet(sheet, "D", row, currency);
+    insert_number_in_sheet(sheet, "E", row, rate);
+
     Ok(())
 }
 
+const SUMMARY_HEADERS: [&str; 4] = ["SteamID", "Sheet", "Items", "Total value"];
+
+/// Rewrites the sheet called `summary_sheet_name` with one row per account (steamid, sheet, item count, total value)
+/// and a total of all of them at the bottom.
+pub fn write_summary_sheet(book: &mut Spreadsheet, summary_sheet_name: &str, rows: &[(u64, String, u32, f64)]) -> Result<(), String> {
+    let sheet: &mut Worksheet = if book.get_sheet_by_name(summary_sheet_name).is_some() {
+        book.get_sheet_by_name_mut(summary_sheet_name)
+            .ok_or_else(|| format!("Failed to get the summary sheet {}.", summary_sheet_name))?
+    } else {
+        book.new_sheet(summary_sheet_name)
+            .map_err(|e| format!("Failed to create the summary sheet {}. {}", summary_sheet_name, e))?
+    };
+
+    // Removes the summary of the last run in case there were more accounts then
+    let old_rows = sheet.get_highest_row();
+    if old_rows > 0 { sheet.remove_row(&1, &old_rows); }
+
+    for (i, header) in SUMMARY_HEADERS.iter().enumerate() {
+        sheet.get_cell_mut(((i + 1) as u32, 1)).set_value(*header);
+    }
+
+    for (i, (steamid, sheet_name, item_count, total_value)) in rows.iter().enumerate() {
+        let row = i + 2;
+
+        insert_string_in_sheet(sheet, "A", row, steamid.to_string());
+        insert_string_in_sheet(sheet, "B", row, sheet_name);
+        insert_number_in_sheet(sheet, "C", row, *item_count);
+        insert_number_in_sheet(sheet, "D", row, *total_value);
+    }
+
+    let total_row = rows.len() + 2;
+    insert_string_in_sheet(sheet, "A", total_row, "Total");
+    insert_number_in_sheet(sheet, "C", total_row, rows.iter().map(|r| r.2).sum::<u32>());
+    insert_number_in_sheet(sheet, "D", total_row, rows.iter().map(|r| r.3).sum::<f64>());
+
+    Ok(())
+}
+
+const REALIZED_HEADERS: [&str; 10] = ["SteamID", "Sheet", "Name", "Quantity", "Sold date", "Sale price", "Buy price", "Fee", "Profit", "Days held"];
+const REALIZED_MONTH_HEADERS: [&str; 4] = ["Month", "Items sold", "Sales", "Profit"];
+
+/// (month, items sold, sales, profit) for every month something was sold, oldest first. Rows without a sold date are put under "Unknown".
+/// Sales are after fees, and rows without a buy price are left out of the profit.
+pub fn realized_by_month(rows: &[RealizedRow]) -> Vec<(String, u32, f64, f64)> {
+    let mut months: BTreeMap<String, (u32, f64, f64)> = BTreeMap::new();
+
+    for row in rows {
+        let month = row.sold_date.map(|d| d.format("%Y-%m").to_string()).unwrap_or_else(|| String::from("Unknown"));
+        let entry = months.entry(month).or_default();
+
+        entry.0 += row.quantity as u32;
+        entry.1 += (row.sale_price - row.fee) * row.quantity as f64;
+        entry.2 += row.profit().unwrap_or(0.0);
+    }
+
+    months.into_iter().map(|(month, (items, sales, profit))| (month, items, sales, profit)).collect()
+}
+
+/// Rewrites the sheet called `realized_sheet_name` with one row per sold row, and under it the totals per month and of everything.
+pub fn write_realized_sheet(book: &mut Spreadsheet, realized_sheet_name: &str, rows: &[RealizedRow]) -> Result<(), String> {
+    let sheet: &mut Worksheet = if book.get_sheet_by_name(realized_sheet_name).is_some() {
+        book.get_sheet_by_name_mut(realized_sheet_name)
+            .ok_or_else(|| format!("Failed to get the realized gains sheet {}.", realized_sheet_name))?
+    } else {
+        book.new_sheet(realized_sheet_name)
+            .map_err(|e| format!("Failed to create the realized gains sheet {}. {}", realized_sheet_name, e))?
+    };
+
+    let old_rows = sheet.get_highest_row();
+    if old_rows > 0 { sheet.remove_row(&1, &old_rows); }
+
+    for (i, header) in REALIZED_HEADERS.iter().enumerate() {
+        sheet.get_cell_mut(((i + 1) as u32, 1)).set_value(*header);
+    }
+
+    for (i, r) in rows.iter().enumerate() {
+        let row = i + 2;
+
+        insert_string_in_sheet(sheet, "A", row, r.steamid.to_string());
+        insert_string_in_sheet(sheet, "B", row, &r.sheet);
+        insert_string_in_sheet(sheet, "C", row, &r.name);
+        insert_number_in_sheet(sheet, "D", row, r.quantity);
+        if let Some(date) = r.sold_date { insert_string_in_sheet(sheet, "E", row, date.format("%Y-%m-%d").to_string()); }
+        insert_number_in_sheet(sheet, "F", row, r.sale_price);
+        if let Some(buy_price) = r.buy_price { insert_number_in_sheet(sheet, "G", row, buy_price); }
+        insert_number_in_sheet(sheet, "H", row, r.fee);
+        if let Some(profit) = r.profit() { insert_number_in_sheet(sheet, "I", row, profit); }
+        if let Some(days) = r.holding_days { insert_number_in_sheet(sheet, "J", row, days as f64); }
+    }
+
+    // One empty row between the items and the months
+    let month_header_row = rows.len() + 3;
+    for (i, header) in REALIZED_MONTH_HEADERS.iter().enumerate() {
+        sheet.get_cell_mut(((i + 1) as u32, month_header_row as u32)).set_value(*header);
+    }
+
+    let months = realized_by_month(rows);
+    for (i, (month, items, sales, profit)) in months.iter().enumerate() {
+        let row = month_header_row + i + 1;
+
+        insert_string_in_sheet(sheet, "A", row, month);
+        insert_number_in_sheet(sheet, "B", row, *items);
+        insert_number_in_sheet(sheet, "C", row, *sales);
+        insert_number_in_sheet(sheet, "D", row, *profit);
+    }
+
+    let total_row = month_header_row + months.len() + 1;
+    insert_string_in_sheet(sheet, "A", total_row, "Total");
+    insert_number_in_sheet(sheet, "B", total_row, months.iter().map(|m| m.1).sum::<u32>());
+    insert_number_in_sheet(sheet, "C", total_row, months.iter().map(|m| m.2).sum::<f64>());
+    insert_number_in_sheet(sheet, "D", total_row, months.iter().map(|m| m.3).sum::<f64>());
+
+    Ok(())
+}
+
+/// Date cells are the serial excel stores them as and the formatted value depends on the number format of the cell (03-04-26),
+/// so numbers are read as serials and written as %Y-%m-%d. Only text cells are kept as they're written.
+fn get_date_cell(sheet: &Worksheet, cell: (u32, u32)) -> Option<String> {
+    let cell = sheet.get_cell(cell)?;
+
+    let date = match cell.get_value_number() {
+        Some(serial) => excel_serial_to_date(serial).map(|d| d.format("%Y-%m-%d").to_string())?,
+        None => cell.get_value().trim().to_string()
+    };
+    (!date.is_empty()).then_some(date)
+}
+
 pub async fn get_exceldata(sheet: &mut Worksheet, excel: &SheetInfo, ignore_sold: bool) -> Result<Vec<ExcelData>, String> {
     let mut exceldata: Vec<ExcelData> = Vec::new();
     let mut iter = excel.row_start_write_in_table;
@@ -72,14 +287,16 @@ pub async fn get_exceldata(sheet: &mut Worksheet, excel: &SheetInfo, ignore_sold
             } else { break }
         };
 
-        // let price_cell = format!("{}{}", excel.col_price, iter);
-        // let price: f64 = {
-        //     if let Some(cell) = sheet.get_cell(price_cell) {
-        //         let cell_value = cell.get_raw_value().to_string().trim().to_string();
-        //         if cell_value.is_empty() { break }
-        //         else { cell_value.parse::<f64>().map_err(|_| "Price failed parsing")? }
-        //     } else { break }
-        // };
+        // Price from the last run, read before it gets overwritten
+        let previous_price: Option<f64> = {
+            if !excel.col_price.is_empty() {
+                let cell_price = (excel.col_price.as_str().to_column().unwrap_or(6), iter);
+
+                sheet.get_cell(cell_price)
+                    .map(|c| c.get_cell_value().get_value())
+                    .and_then(|c| c.trim().parse::<f64>().ok())
+            } else { None }
+        };
 
         // let inspect_link: Option<String> = {
             // if let Some(inspect) = &excel.col_inspect_link {
@@ -126,6 +343,54 @@ pub async fn get_exceldata(sheet: &mut Worksheet, excel: &SheetInfo, ignore_sold
             } else { None }
         };
 
+        let float: Option<f64> = {
+            if let Some(col_float) = &excel.col_float {
+                let cell_float = (col_float.as_str().to_column().unwrap_or(7), iter);
+
+                sheet.get_cell(cell_float)
+                    .map(|c| c.get_cell_value().get_value())
+                    .and_then(|c| c.trim().parse::<f64>().ok())
+            } else { None }
+        };
+
+        let pattern: Option<u32> = {
+            if let Some(col_pattern) = &excel.col_pattern {
+                let cell_pattern = (col_pattern.as_str().to_column().unwrap_or(8), iter);
+
+                sheet.get_cell(cell_pattern)
+                    .map(|c| c.get_cell_value().get_value())
+                    .and_then(|c| c.trim().parse::<u32>().ok())
+            } else { None }
+        };
+
+        let buy_price: Option<f64> = {
+            if let Some(col_buy_price) = &excel.col_buy_price {
+                let cell_buy_price = (col_buy_price.as_str().to_column().unwrap_or(9), iter);
+
+                sheet.get_cell(cell_buy_price)
+                    .map(|c| c.get_cell_value().get_value())
+                    .and_then(|c| c.trim().parse::<f64>().ok())
+            } else { None }
+        };
+
+        let buy_date: Option<String> = {
+            if let Some(col_buy_date) = &excel.col_buy_date {
+                let cell_buy_date = (col_buy_date.as_str().to_column().unwrap_or(10), iter);
+
+                get_date_cell(sheet, cell_buy_date)
+            } else { None }
+        };
+
+        let buy_currency: Option<String> = {
+            if let Some(col_buy_currency) = &excel.col_buy_currency {
+                let cell_buy_currency = (col_buy_currency.as_str().to_column().unwrap_or(11), iter);
+
+                sheet.get_cell(cell_buy_currency)
+                    .map(|c| c.get_cell_value().get_value().trim().to_uppercase())
+                    .filter(|c| !c.is_empty())
+            } else { None }
+        };
+
         let sold: Option<f64> = {
             if ignore_sold {
                 if let Some(col_already_sold) = &excel.col_sold {
@@ -140,10 +405,68 @@ pub async fn get_exceldata(sheet: &mut Worksheet, excel: &SheetInfo, ignore_sold
             } else { None }
         };
 
-        exceldata.push( ExcelData{name, quantity, phase, asset_id, sold} );
+        let sold_date: Option<String> = {
+            if ignore_sold && let Some(col_sold_date) = &excel.col_sold_date {
+                let cell_sold_date = (col_sold_date.as_str().to_column().unwrap_or(12), iter);
+
+                get_date_cell(sheet, cell_sold_date)
+            } else { None }
+        };
+
+        let sold_fee: Option<f64> = {
+            if ignore_sold && let Some(col_sold_fee) = &excel.col_sold_fee {
+                let cell_sold_fee = (col_sold_fee.as_str().to_column().unwrap_or(13), iter);
+
+                sheet.get_cell(cell_sold_fee)
+                    .map(|c| c.get_cell_value().get_value())
+                    .and_then(|c| c.trim().parse::<f64>().ok())
+            } else { None }
+        };
+
+        exceldata.push( ExcelData{name, quantity, phase, asset_id, sold, previous_price, price: None, market: None, net_price: None, float, pattern, buy_price, buy_date, buy_currency, sold_date, sold_fee} );
         iter += 1;
     }
 
     dprintln!("Finished reading excel file.");
     Ok(exceldata)
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::storage::exchange_history::parse_sheet_date;
+
+    fn sold(sold_date: &str, quantity: u16, sale_price: f64, buy_price: Option<f64>, fee: f64) -> RealizedRow {
+        RealizedRow {
+            steamid: 0,
+            sheet: String::from("Sheet1"),
+            name: String::from("AK-47 | Redline (Field-Tested)"),
+            quantity,
+            sold_date: parse_sheet_date(sold_date),
+            sale_price,
+            buy_price,
+            fee,
+            holding_days: None
+        }
+    }
+
+    #[test]
+    fn sales_are_summed_per_month() {
+        let rows = [
+            sold("2026-03-04", 2, 10.0, Some(6.0), 1.0),
+            // Read from an excel date cell
+            sold("46100", 1, 20.0, None, 2.0),
+            sold("2026-01-15", 1, 5.0, Some(7.0), 0.0),
+            sold("03-04-26", 1, 1.0, Some(1.0), 0.0),
+        ];
+
+        assert_eq!(rows[1].sold_date, NaiveDate::from_ymd_opt(2026, 3, 19));
+        assert_eq!(realized_by_month(&rows), vec![
+            (String::from("2026-01"), 1, 5.0, -2.0),
+            (String::from("2026-03"), 3, 36.0, 6.0),
+            (String::from("Unknown"), 1, 1.0, 0.0),
+        ]);
+    }
+}
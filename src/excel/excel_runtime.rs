@@ -2,7 +2,7 @@ use std::{str::FromStr, time::Duration};
 
 use reqwest::Client;
 use strum::IntoEnumIterator;
-use umya_spreadsheet::{Spreadsheet, Worksheet, XlsxError};
+use umya_spreadsheet::{Spreadsheet, Worksheet};
 use serde_json::Value;
 use ahash::{HashMap};
 use indexmap::IndexSet;
@@ -10,14 +10,16 @@ use indexmap::IndexSet;
 use iced::{task::{Straw, sipper}};
 
 use crate::{
-    browser::{csfloat, csgotrader, steamcommunity::SteamInventory}, dprintln, excel::{
-        excel_ops::{get_exceldata, get_spreadsheet, set_spreadsheet}, helpers::{
-            IcedProgressSink, LastInX, ProgressSink, clear_extra_iteminfo_given_quantity, get_cached_markets_data, get_exchange_rate, get_market_price, get_steamloginsecure, insert_new_exceldata, insert_number_in_sheet, insert_string_in_sheet, update_quantity_exceldata, wrapper_fetch_iteminfo_via_itemprovider_persistent
+    browser::{csfloat, csgotrader, price_source::PriceSources, steamcommunity::SteamInventory}, dprintln, excel::{
+        diff::diff_books, excel_ops::{append_value_history, get_exceldata, get_spreadsheet, realized_by_month, set_spreadsheet, write_realized_sheet, write_summary_sheet}, export::{ExportRow, export_path, write_csv, write_jsonl}, ods::{is_ods, write_ods_managed}, helpers::{
+            IcedProgressSink, LastInX, ProgressSink, ToColumn, clear_extra_iteminfo_given_quantity, format_age, get_cached_markets_data, get_exchange_rate, get_exchange_rates, get_market_price, get_steamloginsecure, insert_item_origin, insert_new_exceldata, insert_number_in_sheet, insert_string_in_sheet, update_quantity_exceldata, wrapper_fetch_iteminfo_via_itemprovider_persistent
         },
         helpers::Progress
     }, models::{
-        excel::ExcelData, price::Doppler, user_sheet::{SheetInfo, UserInfo}, web::{ExtraItemData, ItemInfoProvider, Sites, SteamData}
-    }
+        excel::{ExcelData, RealizedRow}, price::{Currencies, Doppler}, user_sheet::{Account, SheetInfo, UserInfo}, web::{ExtraItemData, ItemInfoProvider, Sites, SteamData}
+    },
+    parsing::{manual_items::{load_manual_items, merge_manual_items}, storage_units::{load_storage_units, merge_storage_units}},
+    storage::{backup::backup_sheet, exchange_history::{ExchangeRateHistory, parse_sheet_date}, price_history::PriceHistoryDb}
 };
 
 pub fn run_program_gui(
@@ -31,6 +33,17 @@ pub fn run_program_gui(
     })
 }
 
+/// One account in the run and everything read/fetched for it
+struct AccountRun {
+    account: Account,
+    sheet_name: Option<String>,
+    sm_inv: Option<SteamInventory>,
+    cs_inv: Option<Vec<SteamData>>,
+    had_cookie: bool,
+    exceldata: Vec<ExcelData>,
+    exceldata_initial_length: usize,
+}
+
 pub async fn run_program<P>(
     mut user: UserInfo,
     mut excel: SheetInfo,
@@ -41,6 +54,13 @@ where
 {
     progress.send_str("Running main program:\n\n").await;
 
+    if user.offline {
+        progress.send_str("Running offline. Using the cached inventory, prices and exchange rates no matter how old they are.\n").await;
+
+        // 3rd party iteminfo can't be fetched offline
+        user.iteminfo_provider = ItemInfoProvider::Steam;
+    }
+
     if user.fetch_prices && user.iteminfo_provider != ItemInfoProvider::Steam && excel.col_inspect_link.is_some() {
         progress.send_str("Will Fetch additional iteminfo using 3rd party API. This makes doppler prices accurate.\n").await;
     }
@@ -56,54 +76,145 @@ where
 
     // -----------------------------------------------------------------------------------------------
 
-    let steamcookie: Option<Vec<String>> = if let Some(ref sls) = user.steamloginsecure {
-        Some( Vec::from([sls.to_owned()]) )
-    } else { get_steamloginsecure() };
+    // Without an accounts list it's just the one account set by steamid, steamloginsecure and sheet_name
+    let accounts: Vec<Account> = if user.accounts.is_empty() {
+        Vec::from([ Account {
+            steamid: user.steamid,
+            steamloginsecure: user.steamloginsecure.clone(),
+            sheet_name: excel.sheet_name.clone(),
+            storage_units_path: user.storage_units_path.clone(),
+            manual_items_path: user.manual_items_path.clone()
+        } ])
+    } else { user.accounts.clone() };
 
-    if steamcookie.is_some() { progress.send_str("Found steamcookie(s).\n").await; }
-    else if user.fetch_steam { progress.send_str("Didn't find steamcookie(s).\n").await }
+    let is_multi_account = accounts.len() > 1;
+    let first_steamid = accounts[0].steamid;
 
-    // If multiple cookies found, iterate through them with a delay and hopefully
-    // find the cookie that gives all of the inventory.
-    let sm_inv: Option<SteamInventory> = {
-        if user.fetch_steam {
-            if let Some(cookies) = &steamcookie && !cookies.is_empty() {
-                let mut inv: Option<SteamInventory> = None;
+    // FIRST, FETCH EVERY INVENTORY
 
-                for (i, cookie) in cookies.iter().enumerate() {
-                    let mut cookie_display = cookie.as_str().take_last_x(7);
-                    cookie_display.pop();
+    let mut runs: Vec<AccountRun> = Vec::with_capacity(accounts.len());
 
-                    progress.send_str(
-                        &format!("Attempting to fetch inventory with cookie ending in ...{}\n", cookie_display)
-                    ).await;
+    for account in accounts {
+        if is_multi_account { progress.send_str(&format!("\n--- ACCOUNT {} ---\n", account.steamid)).await; }
 
-                    inv = Some( SteamInventory::init(user.steamid, 730, Some(cookie)).await? );
-                    let inv_tmp = inv.as_ref().unwrap(); // Safe cuz look @ line above lmao
+        let (sm_inv, had_cookie) = fetch_inventory(&user, &account, &mut progress).await?;
 
-                    if inv_tmp.assets_len() == inv_tmp.inventory_len() {
-                        progress.send_str("Found full inventory.\n").await;
-                        break
-                    }
+        let mut cs_inv: Option<Vec<SteamData>> = if let Some(inv) = &sm_inv {
+            Some( inv.get_steam_items(user.group_simular_items, true)? )
+        } else {
+            progress.send_str("Didn't fetch items from cs2 inventory.\n").await;
+            None
+        };
 
-                    if i != cookies.len() && cookies.len() != 1 {
-                        tokio::time::sleep(Duration::from_millis(500)).await;
-                    }
-                }
-                inv
+        if let Some(path) = &account.storage_units_path {
+            let stored = load_storage_units(path)?;
+            let stored_len = stored.len();
+
+            let skipped = merge_storage_units(cs_inv.get_or_insert_with(Vec::new), stored, user.group_simular_items);
+            progress.send_str(&format!("Added {} item(s) from storage units.\n", stored_len - skipped)).await;
 
-            } else { Some( SteamInventory::init(user.steamid, 730, None).await? ) }
+            if skipped != 0 {
+                progress.send_str(&format!("WARNING: Skipped {} storage unit item(s) without an assetid. They're needed when not grouping simular items.\n", skipped)).await;
+            }
         }
-        else { None }
-    };
 
-    let cs_inv: Option<Vec<SteamData>> = if let Some(inv) = &sm_inv {
-        Some( inv.get_steam_items(user.group_simular_items, true)? )
-    } else {
-        progress.send_str("Didn't fetch items from cs2 inventory.\n").await;
-        None
+        if let Some(path) = &account.manual_items_path {
+            let manual = load_manual_items(path)?;
+            let manual_len = manual.len();
+
+            let skipped = merge_manual_items(cs_inv.get_or_insert_with(Vec::new), manual, user.group_simular_items);
+            progress.send_str(&format!("Added {} manual item(s).\n", manual_len - skipped)).await;
+
+            if skipped != 0 {
+                progress.send_str(&format!("WARNING: Skipped {} manual item(s) without an asset_id. They're needed when not grouping simular items.\n", skipped)).await;
+            }
+        }
+
+        runs.push( AccountRun { sheet_name: account.sheet_name.clone(), account, sm_inv, cs_inv, had_cookie, exceldata: Vec::new(), exceldata_initial_length: 0 } );
+    }
+
+    // -----------------------------------------------------------------------------------------------
+
+    // BIG BRAIN; READ THE EXCEL SPREADSHEET FIRST TO GET ALL THE INFO AND THEN GET PRICES WOWOWO
+
+    let is_new_book = excel.path_to_sheet.is_none();
+
+    // Getting the Worksheet from either existing book or new book
+    let mut book: Spreadsheet = get_spreadsheet(
+        &mut excel.path_to_sheet,
+        &mut excel.sheet_name,
+        first_steamid,
+        &mut progress
+    ).await?;
+
+    // Untouched copy to diff against, as nothing is written in a dry run
+    let original_book: Option<Spreadsheet> = if user.dry_run { Some(book.clone()) } else { None };
+
+    // Every account gets its own sheet, named after the steamid if not set. New books only have Sheet1, so the first account uses that
+    if is_multi_account {
+        for (i, run) in runs.iter_mut().enumerate() {
+            if run.sheet_name.is_none() && !(is_new_book && i == 0) { run.sheet_name = Some(run.account.steamid.to_string()); }
+        }
+    } else if is_new_book {
+        runs[0].sheet_name = None;
+    }
+
+    let rate = {
+        let sheet = account_sheet(&mut book, &runs[0].sheet_name, is_multi_account, &excel, &mut progress).await?;
+        get_exchange_rate(&user.usd_to_x, &excel.rowcol_usd_to_x, sheet, user.offline, user.dry_run, &mut progress).await?
     };
 
+    // Buy prices in other currencies are converted with the rates from when they were bought
+    let exchange_history: Option<ExchangeRateHistory> = if excel.col_buy_currency.is_some() && (user.fetch_prices || excel.realized_sheet_name.is_some()) {
+        // get_exchange_rate only fetches when converting to a currency, but todays rates should be in the history anyway
+        if !user.offline && !user.dry_run && matches!(user.usd_to_x, Currencies::None | Currencies::USD)
+            && let Err(e) = get_exchange_rates(false, false, &mut progress).await {
+            progress.send_str(&format!("WARNING: Couldn't fetch todays exchange rates for the history. \n{}\n", e)).await;
+        }
+
+        let history = ExchangeRateHistory::load_default()?;
+        if history.is_empty() {
+            progress.send_str("WARNING: No exchange rate history, buy prices in other currencies are skipped when calculating profit.\n").await;
+        }
+        Some(history)
+    } else { None };
+
+    // -----------------------------------------------------------------------------------------------
+
+    for run in runs.iter_mut() {
+        let sheet = account_sheet(&mut book, &run.sheet_name, is_multi_account, &excel, &mut progress).await?;
+
+        run.exceldata = get_exceldata(sheet, &excel, user.ignore_already_sold).await?;
+        run.exceldata_initial_length = run.exceldata.len();
+        warn_unparsed_dates(&run.exceldata, excel.row_start_write_in_table as usize, &mut progress).await;
+
+        if run.exceldata.is_empty() {
+            progress.send_str("Read empty excel spreadsheet.\n\n").await;
+        } else {
+            let mut exceldata_string = String::with_capacity(256 * run.exceldata_initial_length);
+            exceldata_string.push_str(
+                &if is_multi_account { format!("\nREAD FROM SPREADSHEET ({}):\n", run.account.steamid) }
+                else { String::from("\nREAD FROM SPREADSHEET:\n") }
+            );
+
+            let sold_yes: &'static str = "SOLD: YES";
+            let sold_no: &'static str = "SOLD: NO";
+
+            for data in &run.exceldata {
+                exceldata_string.push_str(
+                    &format!(
+                        "\tNAME: {:-<75} {} {} {}\n",
+                        data.name,
+                        if user.group_simular_items { "QUANTITY:" } else { "ASSETID:" },
+                        if user.group_simular_items { data.quantity.unwrap_or(0) as u64 } else { data.asset_id.unwrap_or(0) },
+                        if user.ignore_already_sold { if data.sold.is_some() {sold_yes} else {sold_no} } else {""}
+                    )
+                );
+            }
+            progress.send_str(exceldata_string.as_str()).await;
+        }
+    }
+
     // -----------------------------------------------------------------------------------------------
 
     let markets_to_check: Option<Vec<Sites>> = if user.fetch_prices {
@@ -115,8 +226,19 @@ where
         )
     } else { None };
 
+    let price_source = PriceSources::new(user.pricing_provider, user.pause_time_ms);
+
+    // Every item that might get priced in every account, for the sources that fetch prices item by item
+    let item_names: Vec<&str> = runs.iter()
+        .flat_map(|run| run.cs_inv.iter().flatten().map(|s| s.name.as_str()).chain( run.exceldata.iter().map(|e| e.name.as_str()) ))
+        .filter(|n| !user.ingore_steam_names.as_ref().is_some_and(|isn| isn.iter().any(|sn| sn.as_str() == *n)))
+        .collect::<IndexSet<&str>>()
+        .into_iter()
+        .collect();
+
+    // Fetched once and shared by all the accounts
     let all_market_prices: Option<HashMap<Sites, Value>> = match &markets_to_check {
-        Some(mtc) => Some( get_cached_markets_data(mtc, user.pricing_provider).await? ),
+        Some(mtc) => Some( get_cached_markets_data(mtc, &price_source, &item_names, &user, &mut progress).await? ),
         None => None
     };
 
@@ -126,70 +248,374 @@ where
         ).await;
     }
 
-    let cs_inv_len: usize = {
-        if let Some(inv) = &cs_inv {
-            progress.send_str("Reading data from cs inventory and applying it to spreadsheet...\n").await;
-            inv.len()
-        }
-        else { 0 }
-    };
-
     // -----------------------------------------------------------------------------------------------
 
-    // BIG BRAIN; READ THE EXCEL SPREADSHEET FIRST TO GET ALL THE INFO AND THEN GET PRICES WOWOWO
+    let finished_at = chrono::Local::now();
+    let finishtime = finished_at
+        .format("%d/%m/%Y %H:%M:%S")
+        .to_string();
 
-    // Getting the Worksheet from either existing book or new book
-    let mut book: Spreadsheet = get_spreadsheet(
-        &mut excel.path_to_sheet,
-        &mut excel.sheet_name,
-        user.steamid,
-        &mut progress
-    ).await?;
+    for run in runs.iter_mut() {
+        if is_multi_account { progress.send_str(&format!("\n--- UPDATING SHEET OF {} ---\n", run.account.steamid)).await; }
+
+        let sheet = account_sheet(&mut book, &run.sheet_name, is_multi_account, &excel, &mut progress).await?;
+
+        update_sheet(
+            &user,
+            &excel,
+            sheet,
+            &run.cs_inv,
+            &mut run.exceldata,
+            run.exceldata_initial_length,
+            &price_source,
+            &markets_to_check,
+            &all_market_prices,
+            rate,
+            &exchange_history,
+            iteminfo_client,
+            &mut progress
+        ).await?;
+
+        if user.reconcile && let Some(cs_inv) = &run.cs_inv {
+            let inventory_lens = run.sm_inv.as_ref().map(|inv| (inv.assets_len(), inv.inventory_len()));
+
+            match reconcile_blocker(inventory_lens) {
+                Some(reason) => progress.send_str(&format!("WARNING: Skipped marking missing items because {}.\n", reason)).await,
+                None => reconcile_missing_items(&user, &excel, sheet, cs_inv, &run.exceldata, run.exceldata_initial_length, &mut progress).await
+            }
+        }
+
+        if let Some(cell_date) = &excel.rowcol_date {
+            sheet.get_cell_value_mut( cell_date.as_str() )
+                .set_value_string( &finishtime );
+        }
+    }
 
-    let sheet: &mut Worksheet = {
-        if let Some(sn) = &excel.sheet_name {
-            if let Some(buk) = book.get_sheet_by_name_mut(sn) { buk }
-            else {
-                dprintln!("WARNING: Automatically fetched first sheet in spreadsheet because {} was not found.\n", sn);
-                progress.send_str(&format!("WARNING: Automatically fetched first sheet in spreadsheet because {} was not found.", sn)).await;
+    // (total value, item count) of every account
+    let totals: Vec<(f64, u32)> = runs.iter()
+        .map(|run| run.exceldata.iter()
+            .filter_map(|data| data.price.map(|p| (p, data.quantity.unwrap_or(1))))
+            .fold((0.0, 0u32), |(total, count), (price, quantity)| (total + price * quantity as f64, count + quantity as u32))
+        )
+        .collect();
+
+    if user.fetch_prices && let Some(summary_sheet_name) = &excel.summary_sheet_name {
+        let rows = runs.iter().zip(&totals)
+            .map(|(run, (total_value, item_count))| (
+                run.account.steamid,
+                run.sheet_name.clone().unwrap_or_else(|| String::from("Sheet1")),
+                *item_count,
+                *total_value
+            ))
+            .collect::<Vec<(u64, String, u32, f64)>>();
+
+        write_summary_sheet(&mut book, summary_sheet_name, &rows)?;
+        progress.send_str(&format!("Wrote the summary of {} account(s) to the sheet {}.\n", rows.len(), summary_sheet_name)).await;
+    }
 
-                book.get_sheet_mut(&0).ok_or_else(||
-                    format!("Failed to get the first sheet in the spreadsheet with path: \n{:?}", excel.path_to_sheet.as_ref())
-                )?
+    if let Some(realized_sheet_name) = &excel.realized_sheet_name {
+        let mut unknown_currencies: IndexSet<&str> = IndexSet::new();
+        let mut rows: Vec<RealizedRow> = Vec::new();
+
+        for run in &runs {
+            for data in &run.exceldata {
+                let Some(sale_price) = data.sold else { continue };
+
+                let buy_price = match buy_price_in_sheet_currency(data, &user.usd_to_x, &exchange_history, rate) {
+                    Some(Ok(buy_price)) => Some(buy_price),
+                    Some(Err(currency)) => { unknown_currencies.insert(currency); None },
+                    None => None
+                };
+
+                let sold_date = data.sold_date.as_deref().and_then(parse_sheet_date);
+                let buy_date = data.buy_date.as_deref().and_then(parse_sheet_date);
+
+                rows.push(RealizedRow {
+                    steamid: run.account.steamid,
+                    sheet: run.sheet_name.clone().unwrap_or_else(|| String::from("Sheet1")),
+                    name: data.name.clone(),
+                    quantity: data.quantity.unwrap_or(1),
+                    sold_date,
+                    sale_price,
+                    buy_price,
+                    fee: data.sold_fee.unwrap_or(0.0),
+                    holding_days: sold_date.zip(buy_date).map(|(sold, bought)| (sold - bought).num_days())
+                });
             }
-        } else { book.get_sheet_mut(&0).ok_or_else(|| "Failed to get first sheet provided by new file creation.")? }
-    };
+        }
 
-    let rate = get_exchange_rate(&user.usd_to_x, &excel.rowcol_usd_to_x, sheet).await?;
+        if !unknown_currencies.is_empty() {
+            progress.send_str(&format!(
+                "WARNING: No exchange rates for {}, realized profit isn't calculated for the items bought in them.\n",
+                unknown_currencies.into_iter().collect::<Vec<&str>>().join(", ")
+            )).await;
+        }
 
-    // -----------------------------------------------------------------------------------------------
+        write_realized_sheet(&mut book, realized_sheet_name, &rows)?;
 
-    let mut exceldata: Vec<ExcelData> = get_exceldata(sheet, &excel, user.ignore_already_sold).await?;
-    let exceldata_initial_length: usize = exceldata.len();
+        let mut realized_string = format!("\nREALIZED GAINS ({} sold row(s), written to the sheet {}):\n", rows.len(), realized_sheet_name);
+        let months = realized_by_month(&rows);
 
-    if exceldata.is_empty() {
-        progress.send_str("Read empty excel spreadsheet.\n\n").await;
-    } else {
-        let mut exceldata_string = String::with_capacity(256 * exceldata_initial_length);
-        exceldata_string.push_str("\nREAD FROM SPREADSHEET:\n");
+        for (month, items, sales, profit) in &months {
+            realized_string.push_str(&format!("\t{:<10} ITEMS: {:<6} SALES: {:<12.2} PROFIT: {:.2}\n", month, items, sales, profit));
+        }
+        realized_string.push_str(&format!(
+            "\t{:<10} ITEMS: {:<6} SALES: {:<12.2} PROFIT: {:.2}\n",
+            "TOTAL",
+            months.iter().map(|m| m.1).sum::<u32>(),
+            months.iter().map(|m| m.2).sum::<f64>(),
+            months.iter().map(|m| m.3).sum::<f64>()
+        ));
+        progress.send_str(&realized_string).await;
+    }
 
-        let sold_yes: &'static str = "SOLD: YES";
-        let sold_no: &'static str = "SOLD: NO";
+    if user.fetch_prices && let Some(history_sheet_name) = &excel.history_sheet_name {
+        let (total_value, item_count) = totals.iter()
+            .fold((0.0, 0u32), |(total, count), (t, c)| (total + t, count + c));
 
-        for data in &exceldata {
-            exceldata_string.push_str(
+        append_value_history(&mut book, history_sheet_name, &finishtime, total_value, item_count, user.usd_to_x.as_str(), rate)?;
+        progress.send_str(&format!("Added total value of {:.2} to the sheet {}.\n", total_value, history_sheet_name)).await;
+    }
+
+    if let Some(original_book) = &original_book {
+        let changes = diff_books(original_book, &book);
+
+        let mut changes_string = String::with_capacity(128 * changes.len() + 64);
+        changes_string.push_str(&format!("\nDRY RUN, {} CELL(S) WOULD CHANGE:\n", changes.len()));
+
+        for change in &changes {
+            changes_string.push_str(
                 &format!(
-                    "\tNAME: {:-<75} {} {} {}\n",
-                    data.name,
-                    if user.group_simular_items { "QUANTITY:" } else { "ASSETID:" },
-                    if user.group_simular_items { data.quantity.unwrap_or(0) as u64 } else { data.asset_id.unwrap_or(0) },
-                    if user.ignore_already_sold { if data.sold.is_some() {sold_yes} else {sold_no} } else {""}
+                    "\tSHEET: {:<15} ROW: {:<5} COL: {:<3} OLD: {:-<40} NEW: {}\n",
+                    change.sheet, change.row, change.col, change.old, change.new
                 )
             );
         }
-        progress.send_str(exceldata_string.as_str()).await;
+        progress.send_str(&changes_string).await;
+
+        progress.send( Progress { message: format!("\nDry run, nothing was written. End time: {}\n", finishtime), percent: 100.0}).await;
+        return Ok(())
+    }
+
+    // Copy of the spreadsheet as it was before this run, in case the run messes it up
+    if let Some(pts) = &excel.path_to_sheet {
+        match backup_sheet(pts, user.backup_count) {
+            Ok(Some(backup)) => progress.send_str(&format!("Backed up the spreadsheet to {}.\n", backup.display())).await,
+            Ok(None) => {},
+            Err(e) => return Err( format!("Didn't write to the spreadsheet because the backup failed. \n{}", e) )
+        }
+    }
+
+    // Writes the modified data to the spreadsheet
+    let written_path = if let Some(pts) = &excel.path_to_sheet && is_ods(pts) {
+        let account_sheets = runs.iter().map(|run| run.sheet_name.clone()).collect::<Vec<Option<String>>>();
+
+        write_ods_managed(pts, &book, &excel, &account_sheets)
+            .map_err(|e| format!("Couldnt write to spreadsheet! : {}", e))?;
+        pts.clone()
+    } else {
+        set_spreadsheet(&excel.path_to_sheet, first_steamid, book).await
+            .map_err(|e| format!("Couldnt write to spreadsheet! : {}", e))?
+    };
+
+    if user.export_csv || user.export_jsonl {
+        let sheet_names: Vec<String> = runs.iter()
+            .map(|run| run.sheet_name.clone().unwrap_or_else(|| String::from("Sheet1")))
+            .collect();
+
+        let rows: Vec<ExportRow> = runs.iter().zip(&sheet_names)
+            .flat_map(|(run, sheet_name)| run.exceldata.iter()
+                .map(|data| ExportRow::new(run.account.steamid, sheet_name, user.usd_to_x.as_str(), data))
+            )
+            .collect();
+
+        for (enabled, extension) in [(user.export_csv, "csv"), (user.export_jsonl, "jsonl")] {
+            if !enabled { continue }
+
+            let path = export_path(&written_path, extension);
+            let written = if extension == "csv" { write_csv(&path, &rows) } else { write_jsonl(&path, &rows) };
+
+            match written {
+                Ok(()) => progress.send_str(&format!("Exported {} rows to {}.\n", rows.len(), path.display())).await,
+                Err(e) => progress.send_str(&format!("WARNING: Failed to export to {}. \n{}\n", extension, e)).await
+            }
+        }
+    }
+
+    // Only saved after the spreadsheet is written so the history matches what's in the spreadsheet.
+    // Offline runs would only save the same cached prices again.
+    if user.fetch_prices && !user.offline {
+        let saved = PriceHistoryDb::open_default()
+            .and_then(|mut db| {
+                runs.iter().try_fold(0, |amount, run|
+                    db.insert_run(finished_at.to_utc(), run.account.steamid, user.usd_to_x.as_str(), rate, &run.exceldata)
+                        .map(|saved| amount + saved)
+                )
+            });
+
+        match saved {
+            Ok(amount) => progress.send_str(&format!("Saved {} prices to price history.\n", amount)).await,
+            Err(e) => progress.send_str(&format!("WARNING: Failed to save price history. \n{}\n", e)).await
+        }
+    }
+
+    for run in &runs {
+        if let Some(inv) = &run.sm_inv {
+            progress.send( Progress {
+                message: format!(
+                    "Fetched items on tradehold{}: {}\n",
+                    if is_multi_account { format!(" for {}", run.account.steamid) } else { String::new() },
+                    if inv.assets_len() == inv.inventory_len() {"YES"}
+                    else if run.had_cookie {"NO. Either cookie it out of date or wrong, or you're not fetching your own inventory."}
+                    else {"NO"}
+                ),
+                percent: 100.0
+            }).await;
+        };
+    }
+
+    progress.send( Progress { message: format!("\nEnd time: {}\n", finishtime), percent: 100.0}).await;
+    Ok(())
+}
+
+/// Fetches the inventory of `account`, or loads it from the cache when offline.
+/// Also returns if a steamcookie was found, which is needed to fetch the items on tradehold.
+async fn fetch_inventory<P>(user: &UserInfo, account: &Account, progress: &mut P) -> Result<(Option<SteamInventory>, bool), String>
+where
+    P: ProgressSink
+{
+    if !user.fetch_steam { return Ok((None, false)) }
+
+    if user.offline {
+        let (inv, saved_at) = SteamInventory::from_cache(account.steamid).await?;
+
+        progress.send_str(
+            &format!("WARNING: Using cached inventory from {} ago.\n", format_age(chrono::Utc::now().signed_duration_since(saved_at)))
+        ).await;
+        return Ok((Some(inv), false))
     }
 
+    let steamcookie: Option<Vec<String>> = if let Some(ref sls) = account.steamloginsecure {
+        Some( Vec::from([sls.to_owned()]) )
+    } else { get_steamloginsecure() };
+
+    if steamcookie.is_some() { progress.send_str("Found steamcookie(s).\n").await; }
+    else { progress.send_str("Didn't find steamcookie(s).\n").await }
+
+    // If multiple cookies found, iterate through them with a delay and hopefully
+    // find the cookie that gives all of the inventory.
+    let sm_inv: Option<SteamInventory> = if let Some(cookies) = &steamcookie && !cookies.is_empty() {
+        let mut inv: Option<SteamInventory> = None;
+        let mut last_error = String::new();
+
+        for (i, cookie) in cookies.iter().enumerate() {
+            let mut cookie_display = cookie.as_str().take_last_x(7);
+            cookie_display.pop();
+
+            progress.send_str(
+                &format!("Attempting to fetch inventory with cookie ending in ...{}\n", cookie_display)
+            ).await;
+
+            match SteamInventory::init(account.steamid, 730, Some(cookie), user.dry_run, progress).await {
+                Ok(fetched) => {
+                    let is_full = fetched.assets_len() == fetched.inventory_len();
+                    inv = Some(fetched);
+
+                    if is_full {
+                        progress.send_str("Found full inventory.\n").await;
+                        break
+                    }
+                },
+                // The next cookie might still work, the cache is only used if none of them do
+                Err(e) => {
+                    progress.send_str(&format!("Failed to fetch inventory with cookie ending in ...{}\n", cookie_display)).await;
+                    last_error = e;
+                }
+            }
+
+            if i + 1 < cookies.len() {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+        }
+
+        match inv {
+            Some(inv) => Some(inv),
+            None => Some( cached_inventory_or_error(account.steamid, last_error, progress).await? )
+        }
+
+    } else {
+        match SteamInventory::init(account.steamid, 730, None, user.dry_run, progress).await {
+            Ok(inv) => Some(inv),
+            Err(e) => Some( cached_inventory_or_error(account.steamid, e, progress).await? )
+        }
+    };
+
+    Ok((sm_inv, steamcookie.is_some()))
+}
+
+/// Gets the sheet called `sheet_name`, or the first sheet if it's None.
+///
+/// Missing sheets are created when running multiple accounts, otherwise the first sheet is used instead.
+async fn account_sheet<'a, P>(
+    book: &'a mut Spreadsheet,
+    sheet_name: &Option<String>,
+    create_missing: bool,
+    excel: &SheetInfo,
+    progress: &mut P
+) -> Result<&'a mut Worksheet, String>
+where
+    P: ProgressSink
+{
+    let Some(sn) = sheet_name else {
+        return book.get_sheet_mut(&0).ok_or_else(|| String::from("Failed to get first sheet provided by new file creation."))
+    };
+
+    if book.get_sheet_by_name(sn).is_some() {
+        return book.get_sheet_by_name_mut(sn).ok_or_else(|| format!("Failed to get the sheet {}.", sn))
+    }
+
+    if create_missing {
+        progress.send_str(&format!("Created the sheet {} as it didn't exist.\n", sn)).await;
+        return book.new_sheet(sn).map_err(|e| format!("Failed to create the sheet {}. {}", sn, e))
+    }
+
+    dprintln!("WARNING: Automatically fetched first sheet in spreadsheet because {} was not found.\n", sn);
+    progress.send_str(&format!("WARNING: Automatically fetched first sheet in spreadsheet because {} was not found.", sn)).await;
+
+    book.get_sheet_mut(&0).ok_or_else(||
+        format!("Failed to get the first sheet in the spreadsheet with path: \n{:?}", excel.path_to_sheet.as_ref())
+    )
+}
+
+/// Inserts the items from the inventory that aren't in the sheet, updates quantities and then the prices of the rest.
+/// `exceldata` gets the new rows pushed and the prices written this run set.
+#[allow(clippy::too_many_arguments)]
+async fn update_sheet<P>(
+    user: &UserInfo,
+    excel: &SheetInfo,
+    sheet: &mut Worksheet,
+    cs_inv: &Option<Vec<SteamData>>,
+    exceldata: &mut Vec<ExcelData>,
+    exceldata_initial_length: usize,
+    price_source: &PriceSources,
+    markets_to_check: &Option<Vec<Sites>>,
+    all_market_prices: &Option<HashMap<Sites, Value>>,
+    rate: f64,
+    exchange_history: &Option<ExchangeRateHistory>,
+    iteminfo_client: &mut Client,
+    progress: &mut P
+) -> Result<(), String>
+where
+    P: ProgressSink
+{
+    let cs_inv_len: usize = {
+        if let Some(inv) = cs_inv {
+            progress.send_str("Reading data from cs inventory and applying it to spreadsheet...\n").await;
+            inv.len()
+        }
+        else { 0 }
+    };
+
     //  exceldata_old_len er her fordi jeg har endret måte å oppdatere prisene i spreadsheet'n på.
     //  Nå, hvis et item fra steam ikke er i spreadsheetn allerede, så oppdateres spreadsheetn med price, quantity,
     //  phase og inspect link. exceldata_old_len skal være til når resten av itemsene skal oppdateres i pris,
@@ -223,6 +649,10 @@ where
         } ).await;
 
         if user.group_simular_items {
+            // Quantity is only lowered when this is the only row and the only item with the name
+            let can_decrease = exceldata.iter().filter(|e| e.name == steamdata.name).count() == 1
+                && cs_inv.iter().flatten().filter(|s| s.name == steamdata.name).count() == 1;
+
             match exceldata.iter_mut().enumerate().find( |(_, e)| e.name == steamdata.name ) {
                 Some((index, data)) => {
 
@@ -230,12 +660,12 @@ where
                     if let Some(ignore) = &user.ingore_steam_names && ignore.iter().any(|n| data.name == *n.trim()) { continue; }
 
                     let row_in_excel: usize = index + excel.row_start_write_in_table as usize;
+                    insert_item_origin(sheet, excel, row_in_excel, steamdata);
 
                     // if exceldatas data has phase info AND user wants to fetch more iteminfo AND cs inventory's steamdata has an inspect link,
                     // don't update quantity and jump to next iteration of cs inv. Instead execute the logic underneath match statement
                     if data.phase.is_some() // data.phase being Some means excel.col_phase has to be Some aswell
-                    && user.iteminfo_provider != ItemInfoProvider::Steam
-                    && steamdata.inspect_link.is_some() {
+                    && (steamdata.phase.is_some() || (user.iteminfo_provider != ItemInfoProvider::Steam && steamdata.inspect_link.is_some())) {
                         // Only path that does not end in a 'continue; keyword. Executes the match statement below this match.
                         // This is needed because you can have two of the same knife, but it can have different phases.
                         // Doing the check here would not cover that possibility so it has to be its´ own loop.
@@ -247,8 +677,8 @@ where
                     && steamdata.inspect_link.is_some()
                     && data.quantity == Some(1)
                     && let Some(col_phase) = &excel.col_phase
-                    && let Some(amp) = &all_market_prices
-                    && let Some(mtc) = &markets_to_check
+                    && let Some(amp) = all_market_prices
+                    && let Some(mtc) = markets_to_check
                     && data.name.to_lowercase().contains(" doppler")
                     {
                         let iteminfo: ExtraItemData = wrapper_fetch_iteminfo_via_itemprovider_persistent(
@@ -257,23 +687,29 @@ where
                             &excel.col_inspect_link,
                             user.pause_time_ms,
                             steamdata,
-                            &mut progress
+                            progress
                         ).await?.ok_or("Iteminfo fetched is None when that shouldnt be possible.".to_string())?;
 
-                        let (market, price) = get_market_price(
-                            &user,
+                        let (market, price, net_price) = get_market_price(
+                            user,
+                            price_source,
                             mtc,
                             amp,
                             rate,
                             &steamdata.name,
                             &iteminfo.phase,
-                            &mut progress
+                            progress
                         ).await?;
 
                         if data.sold.is_none() {
                             if let Some(phase) = &iteminfo.phase { insert_string_in_sheet(sheet, col_phase, row_in_excel, phase.as_str()); }
                             if let Some(price) = price { insert_number_in_sheet(sheet, &excel.col_price, row_in_excel, price); }
-                            if let Some(market) = market && let Some(col_market) = &excel.col_market { insert_string_in_sheet(sheet, col_market, row_in_excel, market); }
+                            if let Some(market) = &market && let Some(col_market) = &excel.col_market { insert_string_in_sheet(sheet, col_market, row_in_excel, market); }
+
+                            data.phase = iteminfo.phase.as_ref().map(|p| p.as_str().to_string());
+                            data.price = price;
+                            data.market = market;
+                            data.net_price = net_price;
                         }
                         continue;
                     }
@@ -281,12 +717,14 @@ where
                     else {
                         update_quantity_exceldata(
                             steamdata,
-                            &excel.col_quantity,
+                            excel,
+                            user.quantity_decrease,
+                            can_decrease,
                             data,
                             row_in_excel,
                             sheet,
-                            &mut progress
-                        ).await;
+                            progress
+                        ).await?;
 
                         // If quantity is more than 1, remove data in float, pattern and inspect_link if its set
                         clear_extra_iteminfo_given_quantity(
@@ -317,21 +755,22 @@ where
                                 &excel.col_inspect_link,
                                 user.pause_time_ms,
                                 steamdata,
-                                &mut progress
+                                progress
                             ).await?
                         }
                         else { None };
 
                     exceldata.push(
                         insert_new_exceldata(
-                            &user, &excel,
+                            user, excel,
+                            price_source,
                             steamdata,
                             &extra_itemdata,
-                            &markets_to_check,
-                            &all_market_prices,
+                            markets_to_check,
+                            all_market_prices,
                             rate, row_in_excel,
                             sheet,
-                            &mut progress
+                            progress
                         ).await?
                     );
                     continue;
@@ -341,9 +780,10 @@ where
 
             // ONLY REACHES HERE IF ITEM HAS PHASE, ITEMINFO PROVIDER IS NOT STEAM AND HAS INSPECT LINK.
 
-            debug_assert!(excel.col_inspect_link.is_some());
-            debug_assert!(steamdata.inspect_link.is_some());
-            debug_assert!(user.iteminfo_provider != ItemInfoProvider::Steam);
+            // Manual items with a phase skip the fetch in the wrapper
+            debug_assert!(steamdata.phase.is_some() || excel.col_inspect_link.is_some());
+            debug_assert!(steamdata.phase.is_some() || steamdata.inspect_link.is_some());
+            debug_assert!(steamdata.phase.is_some() || user.iteminfo_provider != ItemInfoProvider::Steam);
 
             // Only reached when exceldatas name is the same as steamdatas name AND
             // exceldatas phase is something AND user wants to fetch more iteminfo AND
@@ -354,7 +794,7 @@ where
                 &excel.col_inspect_link,
                 user.pause_time_ms,
                 steamdata,
-                &mut progress
+                progress
             ).await?.ok_or("group_simular_items' path for dopplers failed WHAT")?;
 
             let phase: &Option<String> = &extra_itemdata.phase.as_ref()
@@ -363,15 +803,18 @@ where
             match exceldata.iter_mut().enumerate().find( |(_, e)| e.name == steamdata.name && e.phase == *phase ) {
                 Some((index, data)) => {
                     let row_in_excel: usize = index + excel.row_start_write_in_table as usize;
+                    insert_item_origin(sheet, excel, row_in_excel, steamdata);
 
                     update_quantity_exceldata(
                         steamdata,
-                        &excel.col_quantity,
+                        excel,
+                        user.quantity_decrease,
+                        false, // Quantity is for every phase of the item
                         data,
                         row_in_excel,
                         sheet,
-                        &mut progress
-                    ).await;
+                        progress
+                    ).await?;
                 },
                 None => {
 
@@ -383,15 +826,16 @@ where
 
                     exceldata.push(
                         insert_new_exceldata(
-                            &user,
-                            &excel,
+                            user,
+                            excel,
+                            price_source,
                             steamdata,
                             &Some(extra_itemdata),
-                            &markets_to_check,
-                            &all_market_prices,
+                            markets_to_check,
+                            all_market_prices,
                             rate, row_in_excel,
                             sheet,
-                            &mut progress
+                            progress
                         ).await?
                     );
                 }
@@ -404,15 +848,16 @@ where
             // DO NOT INSERT NEW STUFF IF THERE IS A LIMITER ON WHERE TO STOP WRITING
             if excel.row_stop_write_in_table.is_some() { break; }
 
-            match exceldata.iter().enumerate().find(|(_, e)| e.asset_id == Some(steamdata.asset_id) && e.name == steamdata.name) {
+            match exceldata.iter_mut().enumerate().find(|(_, e)| e.asset_id == Some(steamdata.asset_id) && e.name == steamdata.name) {
                 Some((index, data)) => {
+                    insert_item_origin(sheet, excel, index + excel.row_start_write_in_table as usize, steamdata);
 
                     if data.phase.is_none()
                     && user.iteminfo_provider != ItemInfoProvider::Steam
                     && steamdata.inspect_link.is_some()
                     && let Some(col_phase) = &excel.col_phase
-                    && let Some(m_t_c) = &markets_to_check
-                    && let Some(a_m_p) = &all_market_prices
+                    && let Some(m_t_c) = markets_to_check
+                    && let Some(a_m_p) = all_market_prices
                     && data.name.to_lowercase().contains(" doppler")
                     {
                         let row_in_excel: usize = index + excel.row_start_write_in_table as usize;
@@ -423,22 +868,28 @@ where
                             &excel.col_inspect_link,
                             user.pause_time_ms,
                             steamdata,
-                            &mut progress
+                            progress
                         ).await?.ok_or("Iteminfo fetched is None when that shouldnt be possible.".to_string())?;
 
-                        let (market, price) = get_market_price(
-                            &user,
+                        let (market, price, net_price) = get_market_price(
+                            user,
+                            price_source,
                             m_t_c,
                             a_m_p,
                             rate,
                             &steamdata.name,
                             &iteminfo.phase,
-                            &mut progress
+                            progress
                         ).await?;
 
                         if let Some(phase) = &iteminfo.phase { insert_string_in_sheet(sheet, col_phase, row_in_excel, phase.as_str()); }
                         if let Some(price) = price { insert_number_in_sheet(sheet, &excel.col_price, row_in_excel, price); }
-                        if let Some(market) = market && let Some(col_market) = &excel.col_market { insert_string_in_sheet(sheet, col_market, row_in_excel, market); }
+                        if let Some(market) = &market && let Some(col_market) = &excel.col_market { insert_string_in_sheet(sheet, col_market, row_in_excel, market); }
+
+                        data.phase = iteminfo.phase.as_ref().map(|p| p.as_str().to_string());
+                        data.price = price;
+                        data.market = market;
+                        data.net_price = net_price;
                     }
                 }
                 None => {
@@ -450,19 +901,20 @@ where
                         &excel.col_inspect_link,
                         user.pause_time_ms,
                         steamdata,
-                        &mut progress
+                        progress
                     ).await?;
 
                     exceldata.push(
                         insert_new_exceldata(
-                            &user, &excel,
+                            user, excel,
+                            price_source,
                             steamdata,
                             &extra_itemdata,
-                            &markets_to_check,
-                            &all_market_prices,
+                            markets_to_check,
+                            all_market_prices,
                             rate, row_in_excel,
                             sheet,
-                            &mut progress
+                            progress
                         ).await?
                     );
                 }
@@ -480,7 +932,7 @@ where
 
     // Second iteration - updates the prices of all the items other than the
     // one(s) inserted into the spreadsheet during the first iteration.
-    for (i, data) in exceldata.iter().enumerate() {
+    for (i, data) in exceldata.iter_mut().enumerate() {
         if !user.fetch_prices { break }
         if i == exceldata_initial_length { break }
 
@@ -499,54 +951,239 @@ where
         let doppler: Option<Doppler> = data.phase.as_ref()
             .and_then(|p| Doppler::from_str(p).ok());
 
-        let (market, price): (Option<String>, Option<f64>) = if let (Some(amp), Some(mtc)) = (&all_market_prices, &markets_to_check) {
+        let (market, price, net_price): (Option<String>, Option<f64>, Option<f64>) = if let (Some(amp), Some(mtc)) = (all_market_prices, markets_to_check) {
             get_market_price(
-                &user,
+                user,
+                price_source,
                 mtc,
                 amp,
                 rate,
                 data.name.as_str(),
                 &doppler,
-                &mut progress
+                progress
             ).await?
-        } else { (None, None) };
+        } else { (None, None, None) };
 
         if let Some(pris) = price { insert_number_in_sheet(sheet, &excel.col_price, row_in_excel, pris); }
         if let (Some(market), Some(col_market)) = (&market, &excel.col_market) { insert_string_in_sheet(sheet, col_market, row_in_excel, market); }
+
+        data.price = price;
+        data.market = market;
+        data.net_price = net_price;
     }
 
-    let finishtime = chrono::Local::now()
-        .format("%d/%m/%Y %H:%M:%S")
-        .to_string();
+    // Price change since the last run, only for the rows that already had a price
+    if excel.col_previous_price.is_some() || excel.col_price_change.is_some() || excel.col_price_change_pct.is_some() {
+        let change_cols = [&excel.col_previous_price, &excel.col_price_change, &excel.col_price_change_pct];
+
+        for (i, data) in exceldata.iter().enumerate().take(exceldata_initial_length) {
+            if data.sold.is_some() { continue }
+
+            let row_in_excel = i + excel.row_start_write_in_table as usize;
 
-    if let Some(cell_date) = &excel.rowcol_date {
-        sheet.get_cell_value_mut( cell_date.as_str() )
-            .set_value_string( &finishtime );
+            // No price this run, the change from the last run would look like it's current
+            let Some(price) = data.price else {
+                for col in change_cols.into_iter().flatten() { insert_string_in_sheet(sheet, col, row_in_excel, ""); }
+                continue
+            };
+            let Some(previous) = data.previous_price else { continue };
+
+            if let Some(col) = &excel.col_previous_price { insert_number_in_sheet(sheet, col, row_in_excel, previous); }
+            if let Some(col) = &excel.col_price_change { insert_number_in_sheet(sheet, col, row_in_excel, price - previous); }
+            match &excel.col_price_change_pct {
+                Some(col) if previous != 0.0 => insert_number_in_sheet(sheet, col, row_in_excel, (price - previous) / previous * 100.0),
+                Some(col) => insert_string_in_sheet(sheet, col, row_in_excel, ""),
+                None => {}
+            }
+        }
     }
 
-    // Writes the modified data to the spreadsheet
-    set_spreadsheet(&excel.path_to_sheet, user.steamid, book).await
-        .map_err(|e|
-            format!(
-                "Couldnt write to spreadsheet! : {}",
-                if matches!(e, XlsxError::Io(_)) {
-                    format!("{} | Close Excel/LibreOffice/OpenOffice if it is open.", e)
-                } else { e.to_string() }
-            )
-        )?;
+    // Price after the fees of the market(s) the price is from
+    if let Some(col_net_price) = &excel.col_net_price {
+        for (i, data) in exceldata.iter().enumerate() {
+            let Some(net_price) = data.net_price else { continue };
 
-    if let Some(inv) = &sm_inv {
-        progress.send( Progress {
-            message: format!(
-                "Fetched items on tradehold: {}\n",
-                if inv.assets_len() == inv.inventory_len() {"YES"}
-                else if steamcookie.is_some() {"NO. Either cookie it out of date or wrong, or you're not fetching your own inventory."}
-                else {"NO"}
-            ),
-            percent: 100.0
-        }).await;
-    };
+            insert_number_in_sheet(sheet, col_net_price, i + excel.row_start_write_in_table as usize, net_price);
+        }
+    }
+
+    // Profit against what was paid, buy price is per item so it's multiplied by the quantity when grouping
+    if excel.col_profit.is_some() || excel.col_profit_pct.is_some() {
+        let mut unknown_currencies: IndexSet<&str> = IndexSet::new();
+
+        for (i, data) in exceldata.iter().enumerate() {
+            let Some(price) = data.price else { continue };
+
+            let buy_price = match buy_price_in_sheet_currency(data, &user.usd_to_x, exchange_history, rate) {
+                Some(Ok(buy_price)) => buy_price,
+                Some(Err(currency)) => { unknown_currencies.insert(currency); continue },
+                None => continue
+            };
+
+            let row_in_excel = i + excel.row_start_write_in_table as usize;
+            let quantity = data.quantity.unwrap_or(1) as f64;
+
+            if let Some(col) = &excel.col_profit { insert_number_in_sheet(sheet, col, row_in_excel, (price - buy_price) * quantity); }
+            if let Some(col) = &excel.col_profit_pct && buy_price != 0.0 {
+                insert_number_in_sheet(sheet, col, row_in_excel, (price - buy_price) / buy_price * 100.0);
+            }
+        }
+
+        if !unknown_currencies.is_empty() {
+            progress.send_str(&format!(
+                "WARNING: No exchange rates for {}, profit isn't calculated for the items bought in them.\n",
+                unknown_currencies.into_iter().collect::<Vec<&str>>().join(", ")
+            )).await;
+        }
+    }
 
-    progress.send( Progress { message: format!("\nEnd time: {}\n", finishtime), percent: 100.0}).await;
     Ok(())
 }
+
+/// Rows with a date that can't be parsed are treated as if they had none, which picks the newest exchange rate for the buy price
+/// and puts the sale in the Unknown month of the realized sheet.
+async fn warn_unparsed_dates<P>(exceldata: &[ExcelData], row_start: usize, progress: &mut P)
+where
+    P: ProgressSink
+{
+    for (index, data) in exceldata.iter().enumerate() {
+        if let Some(buy_date) = &data.buy_date && parse_sheet_date(buy_date).is_none() {
+            progress.send_str(&format!("WARNING: Couldn't read the buy date '{}' of {} on row {}, use YYYY-MM-DD.\n", buy_date, data.name, index + row_start)).await;
+        }
+        if let Some(sold_date) = &data.sold_date && parse_sheet_date(sold_date).is_none() {
+            progress.send_str(&format!("WARNING: Couldn't read the sold date '{}' of {} on row {}, use YYYY-MM-DD.\n", sold_date, data.name, index + row_start)).await;
+        }
+    }
+}
+
+/// Buy price of `data` in the currency of the sheet, None if it has no buy price.
+/// Buy prices in another currency are converted to USD at the rate closest to the buy date, then to the sheet currency at todays rate.
+/// Err is the currency when there is no exchange rate for it.
+fn buy_price_in_sheet_currency<'a>(
+    data: &'a ExcelData,
+    usd_to_x: &Currencies,
+    exchange_history: &Option<ExchangeRateHistory>,
+    rate: f64
+) -> Option<Result<f64, &'a str>> {
+    let buy_price = data.buy_price?;
+
+    match (&data.buy_currency, exchange_history) {
+        (Some(currency), Some(history)) if !currency.eq_ignore_ascii_case(usd_to_x.as_str()) => {
+            let buy_date = data.buy_date.as_deref().and_then(parse_sheet_date);
+
+            match history.usd_to_x_closest_to(currency, buy_date) {
+                Some(usd_to_buy) if usd_to_buy != 0.0 => Some(Ok(buy_price / usd_to_buy * rate)),
+                _ => Some(Err(currency.as_str()))
+            }
+        },
+        _ => Some(Ok(buy_price))
+    }
+}
+
+/// Why the rows can't be reconciled this run, None if they can. `inventory_lens` is (fetched assets, total inventory count) of the steam inventory.
+/// Storage units and manual items alone aren't the whole inventory, and without the items on tradehold they would all be marked as missing.
+fn reconcile_blocker(inventory_lens: Option<(usize, usize)>) -> Option<&'static str> {
+    match inventory_lens {
+        None => Some("the steam inventory wasn't fetched"),
+        Some((assets, total)) if assets != total => Some("the items on tradehold weren't fetched"),
+        Some(_) => None
+    }
+}
+
+/// New strikethrough of a name if it has to change, so the formatting of the rest of the rows is left alone.
+fn strikethrough_change(is_missing: bool, is_struck_through: bool) -> Option<bool> {
+    (is_missing != is_struck_through).then_some(is_missing)
+}
+
+/// Marks the rows that were in the sheet before this run as missing if they're not in `cs_inv` anymore (traded, sold etc.)
+/// and as in the inventory if they are, so items that come back get unmarked.
+/// Missing rows are marked in col_status, or with a strikethrough on the name if there is no status column.
+/// The strikethrough is only set on rows that went missing and cleared on rows that came back.
+async fn reconcile_missing_items<P>(
+    user: &UserInfo,
+    excel: &SheetInfo,
+    sheet: &mut Worksheet,
+    cs_inv: &[SteamData],
+    exceldata: &[ExcelData],
+    exceldata_initial_length: usize,
+    progress: &mut P
+)
+where
+    P: ProgressSink
+{
+    let mut missing: usize = 0;
+
+    // Only rows read from the sheet are checked, the new ones are from the inventory
+    for (index, data) in exceldata.iter().take(exceldata_initial_length).enumerate() {
+        if data.sold.is_some() { continue }
+
+        let row_in_excel: usize = index + excel.row_start_write_in_table as usize;
+
+        let is_missing = if user.group_simular_items {
+            !cs_inv.iter().any(|s| s.name == data.name)
+        } else {
+            !cs_inv.iter().any(|s| Some(s.asset_id) == data.asset_id && s.name == data.name)
+        };
+
+        if is_missing {
+            missing += 1;
+            progress.send_str(&format!("\t* MISSING: {:-<75} ROW: {}\n", data.name, row_in_excel)).await;
+        }
+
+        match &excel.col_status {
+            Some(col_status) => insert_string_in_sheet(sheet, col_status, row_in_excel, if is_missing { "Missing" } else { "In inventory" }),
+            None => {
+                let name_cell = (excel.col_steam_name.as_str().to_column().unwrap_or(1), row_in_excel as u32);
+                let is_struck_through = sheet.get_style(name_cell).get_font().is_some_and(|f| *f.get_strikethrough());
+
+                if let Some(strikethrough) = strikethrough_change(is_missing, is_struck_through) {
+                    sheet.get_style_mut(name_cell).get_font_mut().set_strikethrough(strikethrough);
+                }
+            }
+        }
+    }
+
+    progress.send_str(&format!("{} row(s) are no longer in the inventory.\n", missing)).await;
+}
+
+/// Falls back to the cached inventory when fetching it from steam fails (rate limits mostly), and errors with `fetch_error` if there is none.
+async fn cached_inventory_or_error<P>(steamid: u64, fetch_error: String, progress: &mut P) -> Result<SteamInventory, String>
+where
+    P: ProgressSink
+{
+    match SteamInventory::from_cache(steamid).await {
+        Ok((inv, saved_at)) => {
+            progress.send_str(
+                &format!(
+                    "WARNING: Failed to fetch inventory from steam, using cached inventory from {} ago instead. \n{}\n",
+                    format_age(chrono::Utc::now().signed_duration_since(saved_at)),
+                    fetch_error
+                )
+            ).await;
+            Ok(inv)
+        },
+        Err(_) => Err(fetch_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconciles_only_a_complete_steam_inventory() {
+        // Storage units or manual items without the steam inventory
+        assert_eq!(reconcile_blocker(None), Some("the steam inventory wasn't fetched"));
+        assert_eq!(reconcile_blocker(Some((10, 12))), Some("the items on tradehold weren't fetched"));
+        assert_eq!(reconcile_blocker(Some((12, 12))), None);
+        assert_eq!(reconcile_blocker(Some((0, 0))), None);
+    }
+
+    #[test]
+    fn strikethrough_only_changes_with_the_missing_state() {
+        assert_eq!(strikethrough_change(true, false), Some(true));
+        assert_eq!(strikethrough_change(false, true), Some(false));
+        assert_eq!(strikethrough_change(true, true), None);
+        assert_eq!(strikethrough_change(false, false), None);
+    }
+}
@@ -0,0 +1,128 @@
+use std::{fs::File, io::{BufWriter, Write}, path::{Path, PathBuf}};
+
+use serde::Serialize;
+
+use crate::models::excel::ExcelData;
+
+const CSV_HEADERS: [&str; 17] = [
+    "steamid", "sheet", "name", "quantity", "asset_id", "phase", "float", "pattern",
+    "price", "previous_price", "market", "net_price", "sold", "buy_price", "buy_date", "buy_currency", "currency"
+];
+
+/// One row of the export, which is the final `ExcelData` of a row and what account it belongs to
+#[derive(Debug, Serialize)]
+pub struct ExportRow<'a> {
+    pub steamid: u64,
+    pub sheet: &'a str,
+    pub name: &'a str,
+    pub quantity: Option<u16>,
+    pub asset_id: Option<u64>,
+    pub phase: Option<&'a str>,
+    pub float: Option<f64>,
+    pub pattern: Option<u32>,
+    pub price: Option<f64>,
+    pub previous_price: Option<f64>,
+    pub market: Option<&'a str>,
+    pub net_price: Option<f64>,
+    pub sold: Option<f64>,
+    pub buy_price: Option<f64>,
+    pub buy_date: Option<&'a str>,
+    pub buy_currency: Option<&'a str>,
+    pub currency: &'a str,
+}
+
+impl<'a> ExportRow<'a> {
+    pub fn new(steamid: u64, sheet: &'a str, currency: &'a str, data: &'a ExcelData) -> Self {
+        ExportRow {
+            steamid,
+            sheet,
+            name: &data.name,
+            quantity: data.quantity,
+            asset_id: data.asset_id,
+            phase: data.phase.as_deref(),
+            float: data.float,
+            pattern: data.pattern,
+            price: data.price,
+            previous_price: data.previous_price,
+            market: data.market.as_deref(),
+            net_price: data.net_price,
+            sold: data.sold,
+            buy_price: data.buy_price,
+            buy_date: data.buy_date.as_deref(),
+            buy_currency: data.buy_currency.as_deref(),
+            currency,
+        }
+    }
+
+    fn csv_fields(&self) -> [String; 17] {
+        fn opt<T: ToString>(v: &Option<T>) -> String { v.as_ref().map(|v| v.to_string()).unwrap_or_default() }
+
+        [
+            self.steamid.to_string(),
+            self.sheet.to_string(),
+            self.name.to_string(),
+            opt(&self.quantity),
+            opt(&self.asset_id),
+            opt(&self.phase),
+            opt(&self.float),
+            opt(&self.pattern),
+            opt(&self.price),
+            opt(&self.previous_price),
+            opt(&self.market),
+            opt(&self.net_price),
+            opt(&self.sold),
+            opt(&self.buy_price),
+            opt(&self.buy_date),
+            opt(&self.buy_currency),
+            self.currency.to_string(),
+        ]
+    }
+}
+
+/// Quotes the field if it has a comma, quote or newline in it. Item names like `Sticker | Team (Holo) | Katowice 2014` are fine as is,
+/// but `"` shows up in some name tags.
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Path of the export next to the spreadsheet, ex: `sheet.xlsx` -> `sheet.csv`
+pub fn export_path(sheet_path: &Path, extension: &str) -> PathBuf {
+    sheet_path.with_extension(extension)
+}
+
+pub fn write_csv(path: &Path, rows: &[ExportRow]) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Couldn't create {}. \n{}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+
+    let write_err = |e: std::io::Error| format!("Couldn't write to {}. \n{}", path.display(), e);
+
+    writeln!(writer, "{}", CSV_HEADERS.join(",")).map_err(write_err)?;
+
+    for row in rows {
+        let line = row.csv_fields()
+            .iter()
+            .map(|f| csv_escape(f))
+            .collect::<Vec<String>>()
+            .join(",");
+
+        writeln!(writer, "{}", line).map_err(write_err)?;
+    }
+    writer.flush().map_err(write_err)
+}
+
+pub fn write_jsonl(path: &Path, rows: &[ExportRow]) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Couldn't create {}. \n{}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+
+    let write_err = |e: std::io::Error| format!("Couldn't write to {}. \n{}", path.display(), e);
+
+    for row in rows {
+        let line = serde_json::to_string(row).map_err(|e| format!("Couldn't serialize the row of {}. \n{}", row.name, e))?;
+        writeln!(writer, "{}", line).map_err(write_err)?;
+    }
+    writer.flush().map_err(write_err)
+}
@@ -1,6 +1,6 @@
 use std::{env, path::{Path, PathBuf}, future::Future};
 use ahash::{HashMap, HashMapExt};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde_json::Value;
 use sipper::Sender;
 use tokio::{fs, io::AsyncWriteExt};
@@ -10,23 +10,23 @@ use umya_spreadsheet::Worksheet;
 
 use crate::{
     browser::{
-        cookies::FirefoxDb, csfloat, csgotrader
+        cookies::FirefoxDb, csfloat, csgotrader, price_source::PriceSource
     },
     dprintln,
     models::{
         excel::ExcelData,
         price::{
-            Currencies, Doppler, PriceType, PricingMode, PricingProvider
+            Currencies, Doppler, PriceType, PricingMode
         },
-        user_sheet::{SheetInfo, UserInfo},
+        user_sheet::{QuantityDecrease, SheetInfo, UserInfo},
         web::{
             CachedMarket, ExtraItemData, ItemInfoProvider, Sites, SteamData
         }
     },
     parsing::{
-        self, csgoskins_url, item_csgotrader, market_name_parse
+        self, csgoskins_url, market_name_parse
     },
-    CACHE_TIME
+    storage::{cache::{cache_dir, market_cache_path}, exchange_history::ExchangeRateHistory}
 };
 
 pub fn get_steamloginsecure() -> Option<Vec<String>> {
@@ -38,16 +38,23 @@ pub fn get_steamloginsecure() -> Option<Vec<String>> {
     } else { dprintln!("WARNING: Failed to connect to firefox."); None }
 }
 
-pub async fn get_exchange_rate(
+pub async fn get_exchange_rate<P>(
     usd_to_x: &Currencies,
     rowcol_usd_to_x: &Option<String>,
-    sheet: &mut Worksheet
-) -> Result<f64, String> {
+    sheet: &mut Worksheet,
+    offline: bool,
+    dry_run: bool,
+    progress: &mut P
+) -> Result<f64, String>
+where
+    P: ProgressSink
+{
 
     if usd_to_x != &Currencies::None {
         if usd_to_x == &Currencies::USD { return Ok(1.0); }
 
-        let rates: HashMap<String, f64> = csgotrader::get_exchange_rates().await?;
+        let rates = get_exchange_rates(offline, dry_run, progress).await?;
+
         rates.get( usd_to_x.as_str() ).copied().ok_or( String::from("Chosen currency not found :(") )
 
     } else if let Some(cell) = rowcol_usd_to_x {
@@ -67,23 +74,78 @@ pub async fn get_exchange_rate(
     } else { Ok(1.0) }
 }
 
-pub async fn get_market_price<P>(
+/// All the USD to x rates from csgotrader, or the cache when offline.
+/// Fetched rates are also saved to the exchange rate history, which buy prices in other currencies are converted with.
+/// Nothing is saved in a dry run.
+pub async fn get_exchange_rates<P>(offline: bool, dry_run: bool, progress: &mut P) -> Result<HashMap<String, f64>, String>
+where
+    P: ProgressSink
+{
+    let cache_path = cache_dir().join("exchange_rates_cache.json");
+
+    if offline {
+        let cached = load_cache(&cache_path).await
+            .map_err(|e| format!("No cached exchange rates to run offline with. \n{}", e))?;
+
+        progress.send_str(
+            &format!("WARNING: Using cached exchange rates from {} ago.\n", format_age(Utc::now().signed_duration_since(cached.timestamp)))
+        ).await;
+
+        serde_json::from_value(cached.prices).map_err(|e| format!("Cached exchange rates are invalid. \n{}", e))
+    } else {
+        let rates = csgotrader::get_exchange_rates().await?;
+        if dry_run { return Ok(rates) }
+
+        // Only needed for offline runs, so the run doesn't fail if it can't be saved
+        if let Ok(value) = serde_json::to_value(&rates) && let Err(_e) = save_cache(&cache_path, value, Utc::now()).await {
+            dprintln!("Failed to cache exchange rates: {}", _e);
+        }
+        // Same with the history, it only makes converting old buy prices less accurate
+        if let Err(_e) = ExchangeRateHistory::save_snapshot(&rates, chrono::Local::now().date_naive()) {
+            dprintln!("Failed to save exchange rates to the history: {}", _e);
+        }
+        Ok(rates)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct MarketPrice { market: Sites, price: f64 }
+
+/// Median of `values`, which have to be sorted
+fn median(values: &[f64]) -> f64 {
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] }
+}
+
+/// Cheapest and most expensive are left out when there's enough markets, so one odd market doesn't drag the average.
+/// `sorted` has to be sorted by price, the bool is if anything was left out.
+fn trim_outliers<T>(sorted: &[T]) -> (&[T], bool) {
+    if sorted.len() >= 4 { (&sorted[1..sorted.len() - 1], true) } else { (sorted, false) }
+}
+
+fn mean(values: impl ExactSizeIterator<Item = f64>) -> f64 {
+    let len = values.len() as f64;
+    values.sum::<f64>() / len
+}
+
+/// (market, price, net price) of the item. The net price is what's left after the fees of the market,
+/// for Median and Average it's the median or average of what's left on each market used.
+pub async fn get_market_price<P, S>(
     user: &UserInfo,
+    price_source: &S,
     markets_to_check: &Vec<Sites>,
     all_market_prices: &HashMap<Sites, Value>,
     rate: f64,
     item_name: &str,
     doppler: &Option<Doppler>,
     progress: &mut P
-) -> Result<(Option<String>, Option<f64>), String>
+) -> Result<(Option<String>, Option<f64>, Option<f64>), String>
 where
-    P: ProgressSink
+    P: ProgressSink,
+    S: PriceSource
 {
-    if !user.fetch_prices { Ok((None, None)) }
+    if !user.fetch_prices { Ok((None, None, None)) }
     else {
-        #[derive(Clone, Copy)]
-        struct MarketPrice { market: &'static str, price: f64 }
-
         let mut prices: Vec<MarketPrice> = Vec::new();
 
         // Finds the prices for each market
@@ -92,32 +154,41 @@ where
             if doppler.is_some() && !market.has_doppler() { continue; }
 
             if let Some(market_prices) = all_market_prices.get(market)
-            && let Some(price) = item_csgotrader::get_price(
+            && let Some(price) = price_source.get_price(
                 item_name,
                 market_prices,
                 *market,
                 PriceType::StartingAt,
                 doppler,
                 progress
-            ).await? { prices.push( MarketPrice { market: market.as_str(), price: price * rate } ) }
+            ).await? { prices.push( MarketPrice { market: *market, price: price * rate } ) }
 
         }
-        if prices.is_empty() { Ok((Some("No Market(s) Found".to_string()), None)) }
+        // Median and Average aren't from one market, so the market column says which ones were used
+        let contributed = |mode: &str, mps: &[MarketPrice]| format!(
+            "{} of {} ({})",
+            mode,
+            mps.len(),
+            mps.iter().map(|mp| mp.market.as_str()).collect::<Vec<&str>>().join(", ")
+        );
+        let from_one = |mp: MarketPrice| Ok((Some(mp.market.as_str().to_string()), Some(mp.price), Some(user.net_price(mp.market, mp.price))));
+
+        if prices.is_empty() { Ok((Some("No Market(s) Found".to_string()), None, None)) }
         else {
             match user.pricing_mode {
                 PricingMode::Cheapest => {
                     prices.sort_by(|a,b| a.price.partial_cmp(&b.price).unwrap());
-                    Ok((Some(prices[0].market.to_string()), Some(prices[0].price)))
+                    from_one(prices[0])
                 },
                 PricingMode::MostExpensive => {
                     prices.sort_by(|a,b| b.price.partial_cmp(&a.price).unwrap());
-                    Ok((Some(prices[0].market.to_string()), Some(prices[0].price)))
+                    from_one(prices[0])
                 },
                 PricingMode::Random => {
                     let wiener = prices.get( rand::random_range(0..prices.len()) )
                         .ok_or("PricingMode::Random failed what.")
                         .copied()?;
-                    Ok((Some(wiener.market.to_string()), Some(wiener.price)))
+                    from_one(wiener)
                 },
                 PricingMode::Hierarchical => {
                     prices.sort_by(|a,b| a.price.partial_cmp(&b.price).unwrap());
@@ -126,7 +197,32 @@ where
                         if curr.price > mp.price * user.percent_threshold as f64
                         { curr = *mp } else { break }
                     }
-                    Ok((Some(curr.market.to_string()), Some(curr.price)) )
+                    from_one(curr)
+                },
+                PricingMode::BestNet => {
+                    // The gross price is still what's written, only the market is chosen by what you'd get after the fee
+                    prices.sort_by(|a,b| user.net_price(b.market, b.price).partial_cmp(&user.net_price(a.market, a.price)).unwrap());
+                    from_one(prices[0])
+                },
+                PricingMode::Median => {
+                    prices.sort_by(|a,b| a.price.partial_cmp(&b.price).unwrap());
+                    let gross = prices.iter().map(|mp| mp.price).collect::<Vec<f64>>();
+
+                    // Fees differ per market, so the net prices are sorted on their own
+                    let mut net = prices.iter().map(|mp| user.net_price(mp.market, mp.price)).collect::<Vec<f64>>();
+                    net.sort_by(|a,b| a.partial_cmp(b).unwrap());
+
+                    Ok((Some(contributed("median", &prices)), Some(median(&gross)), Some(median(&net))))
+                },
+                PricingMode::Average => {
+                    prices.sort_by(|a,b| a.price.partial_cmp(&b.price).unwrap());
+                    let (used, trimmed) = trim_outliers(&prices);
+
+                    let average = mean(used.iter().map(|mp| mp.price));
+                    let net = mean(used.iter().map(|mp| user.net_price(mp.market, mp.price)));
+                    let mode = if trimmed { "trimmed average" } else { "average" };
+
+                    Ok((Some(contributed(mode, used)), Some(average), Some(net)))
                 }
             }
         }
@@ -177,6 +273,10 @@ pub async fn wrapper_fetch_iteminfo_via_itemprovider_persistent<P>(
 where
     P: ProgressSink
 {
+    // Manual items have their phase written in the file, so there's nothing to fetch
+    if steamdata.phase.is_some() {
+        return Ok(Some( ExtraItemData { phase: steamdata.phase.clone(), paintseed: steamdata.pattern, float: steamdata.float } ))
+    }
 
     let json_response = fetch_iteminfo_via_itemprovider_persistent(
         client,
@@ -202,9 +302,10 @@ where
     } else { Ok(None) }
 }
 
-pub async fn insert_new_exceldata<P>(
+pub async fn insert_new_exceldata<P, S>(
     user: &UserInfo,
     excel: &SheetInfo,
+    price_source: &S,
     steamdata: &SteamData,
     extra_itemdata: &Option<ExtraItemData>,
     markets_to_check: &Option<Vec<Sites>>,
@@ -215,15 +316,17 @@ pub async fn insert_new_exceldata<P>(
     progress: &mut P,
 ) -> Result<ExcelData, String>
 where
-    P: ProgressSink
+    P: ProgressSink,
+    S: PriceSource
 {
 
     let doppler: Option<Doppler> = extra_itemdata.as_ref()
         .and_then(|ei| ei.phase.clone());
 
-    let (market, price): (Option<String>, Option<f64>) = if let Some(m_t_c) = markets_to_check && let Some(a_m_p) = all_market_prices {
+    let (market, price, net_price): (Option<String>, Option<f64>, Option<f64>) = if let Some(m_t_c) = markets_to_check && let Some(a_m_p) = all_market_prices {
         get_market_price(
             user,
+            price_source,
             m_t_c,
             a_m_p,
             rate,
@@ -231,7 +334,7 @@ where
             &doppler,
             progress
         ).await?
-    } else { (None, None) };
+    } else { (None, None, None) };
 
     // Inserting into the spreadsheet
     insert_string_in_sheet(sheet, &excel.col_steam_name, row_in_excel, &steamdata.name);
@@ -246,9 +349,10 @@ where
 
     if let Some(col_quantity)     = &excel.col_quantity && let Some(quantity) = steamdata.quantity              { insert_number_in_sheet(sheet, col_quantity, row_in_excel, quantity); }
     if let Some(monetary)         = price                                                                       { insert_number_in_sheet(sheet, &excel.col_price, row_in_excel, monetary); }
-    if let Some(col_market)       = &excel.col_market && let Some(marquet) = market                             { insert_string_in_sheet(sheet, col_market, row_in_excel, &marquet); }
+    if let Some(col_market)       = &excel.col_market && let Some(marquet) = &market                            { insert_string_in_sheet(sheet, col_market, row_in_excel, marquet); }
     if let Some(col_inspect_link) = &excel.col_inspect_link && let Some(inspect_link) = &steamdata.inspect_link { insert_string_in_sheet(sheet, col_inspect_link, row_in_excel, inspect_link); }
     if let Some(col_asset_id)     = &excel.col_asset_id && !user.group_simular_items                            { insert_number_in_sheet(sheet, col_asset_id, row_in_excel, steamdata.asset_id as f64); }
+    insert_item_origin(sheet, excel, row_in_excel, steamdata);
 
     if steamdata.quantity == Some(1) || steamdata.quantity.is_none() {
         if let Some(itemdata) = extra_itemdata {
@@ -270,45 +374,90 @@ where
 
     progress.send_str(&format!("\t* INSERTING: {:-<75} | ROW: {}\n", &steamdata.name, row_in_excel)).await;
 
+    // Same as what got written to the float and pattern columns
+    let (float, pattern) = if steamdata.quantity == Some(1) || steamdata.quantity.is_none() {
+        match extra_itemdata {
+            Some(itemdata) => (itemdata.float, itemdata.paintseed),
+            None => (steamdata.float, steamdata.pattern)
+        }
+    } else { (None, None) };
+
     Ok(ExcelData {
         name: steamdata.name.clone(),
         quantity: steamdata.quantity,
         phase: doppler.as_ref().map(|d| d.as_str().to_string()),
-        // price: price.map_or_else(|| 0.0, |p| p),
         // inspect_link: steamdata.inspect_link.clone(),
         asset_id: if !user.group_simular_items { Some(steamdata.asset_id) } else { None },
-        sold: None
+        sold: None,
+        previous_price: None,
+        price,
+        market,
+        net_price,
+        float,
+        pattern,
+        buy_price: None,
+        buy_date: None,
+        buy_currency: None,
+        sold_date: None,
+        sold_fee: None
     })
 }
 
+/// Raises the quantity in the sheet to what's in the inventory, and lowers it if `quantity_decrease` allows it.
+///
+/// `can_decrease` has to be false when the quantity of `steamdata` isn't only for this row
+/// (dopplers with different phases are grouped under the same name in the inventory).
+#[allow(clippy::too_many_arguments)]
 pub async fn update_quantity_exceldata<P>(
     steamdata: &SteamData,
-    col_quantity: &Option<String>,
+    excel: &SheetInfo,
+    quantity_decrease: QuantityDecrease,
+    can_decrease: bool,
     data: &mut ExcelData,
     row_in_excel: usize,
     sheet: &mut Worksheet,
     progress: &mut P,
-)
+) -> Result<(), String>
 where
     P: ProgressSink
 {
-    if data.sold.is_none()
-    && let Some(col_quantity) = col_quantity
-    && let Some(steam_quantity) = steamdata.quantity
-    && let Some(data_quantity) = data.quantity
-    && data_quantity < steam_quantity
-    {
+    let (Some(col_quantity), Some(steam_quantity), Some(data_quantity)) = (&excel.col_quantity, steamdata.quantity, data.quantity) else { return Ok(()) };
+
+    if data.sold.is_some() || data_quantity == steam_quantity { return Ok(()) }
+
+    if data_quantity < steam_quantity {
         progress.send_str(&format!(
             "\t* UPDATING QUANTITY OF {:-<75} FROM => {} TO => {} ROW: {}\n",
             &steamdata.name,
-            &data.quantity.unwrap_or(0),
+            data_quantity,
+            steam_quantity,
+            &row_in_excel)
+        ).await;
+    }
+    else if can_decrease && quantity_decrease != QuantityDecrease::Never {
+        progress.send_str(&format!(
+            "\t* LOWERING QUANTITY OF {:-<75} FROM => {} TO => {} ROW: {}\n",
+            &steamdata.name,
+            data_quantity,
             steam_quantity,
             &row_in_excel)
         ).await;
 
-        data.quantity = Some(steam_quantity);
-        insert_number_in_sheet(sheet, col_quantity, row_in_excel, steam_quantity);
+        if quantity_decrease == QuantityDecrease::Record && let Some(col_removed) = &excel.col_quantity_removed {
+            // Only checked by the sanitizer when running from the gui
+            let col = col_removed.as_str().to_column()
+                .ok_or_else(|| format!("Quantity removed column '{}' is invalid.", col_removed))?;
+            let cell = (col, row_in_excel as u32);
+            let removed_before = sheet.get_cell(cell).and_then(|c| c.get_value_number()).unwrap_or(0.0);
+
+            insert_number_in_sheet(sheet, col_removed, row_in_excel, removed_before + (data_quantity - steam_quantity) as f64);
+        }
     }
+    else { return Ok(()) }
+
+    data.quantity = Some(steam_quantity);
+    insert_number_in_sheet(sheet, col_quantity, row_in_excel, steam_quantity);
+    Ok(())
 }
 
 #[inline]
@@ -323,38 +472,87 @@ pub fn insert_string_in_sheet(sheet: &mut Worksheet, col: &str, row_in_excel: us
     sheet.get_cell_mut(cell).set_value(value);
 }
 
-pub async fn get_cached_markets_data(markets_to_check: &Vec<Sites>, pricing_provider: PricingProvider) -> Result<HashMap<Sites, serde_json::Value>, String> {
+pub async fn get_cached_markets_data<S, P>(
+    markets_to_check: &Vec<Sites>,
+    price_source: &S,
+    item_names: &[&str],
+    user: &UserInfo,
+    progress: &mut P
+) -> Result<HashMap<Sites, serde_json::Value>, String>
+where
+    S: PriceSource,
+    P: ProgressSink
+{
     let mut amp: HashMap<Sites, Value> = HashMap::new();
 
-    let cache_dir = dirs::cache_dir()
-        .unwrap_or(std::env::temp_dir())
-        .join("cs2excel\\cache");
-
     for market in markets_to_check {
-        let market_prices = match pricing_provider {
-            PricingProvider::Csgoskins => { // IF I IMPLEMENT CSGOSKINS IN THE FUTURE
-                get_cached_market_data(cache_dir.as_path(), PricingProvider::Csgotrader, *market, csgotrader::get_market_data).await?
-            },
-            PricingProvider::Csgotrader => {
-                get_cached_market_data(cache_dir.as_path(), pricing_provider, *market, csgotrader::get_market_data).await?
-            },
-        };
+        if user.offline {
+            match get_offline_market_data(price_source, *market, user.cache_ttl(*market), progress).await? {
+                Some(market_prices) => { amp.insert(market.to_owned(), market_prices); },
+                None => { progress.send_str(&format!("WARNING: No cached prices for {}, skipping it since running offline.\n", market)).await; }
+            }
+            continue;
+        }
 
+        let market_prices = get_cached_market_data(
+            price_source,
+            *market,
+            item_names,
+            user.cache_ttl(*market),
+            user.force_refresh,
+            user.dry_run,
+            progress
+        ).await?;
         amp.insert(market.to_owned(), market_prices);
     }
     Ok(amp)
 }
 
+/// Formats how old something is as "2d 3h", "3h 12m" or "12m"
+pub fn format_age(elapsed: chrono::TimeDelta) -> String {
+    let minutes = elapsed.num_minutes().max(0);
+
+    if minutes >= 60 * 24 { format!("{}d {}h", minutes / (60 * 24), minutes % (60 * 24) / 60) }
+    else if minutes >= 60 { format!("{}h {}m", minutes / 60, minutes % 60) }
+    else { format!("{}m", minutes) }
+}
+
+/// Loads the cached market no matter how old it is. None if there is no cache for the market.
+async fn get_offline_market_data<S, P>(
+    price_source: &S,
+    market: Sites,
+    ttl: std::time::Duration,
+    progress: &mut P
+) -> Result<Option<serde_json::Value>, String>
+where
+    S: PriceSource,
+    P: ProgressSink
+{
+    let cache_path = market_cache_path(market, price_source.name());
+
+    if !cache_path.exists() { return Ok(None) }
+
+    let cm = load_cache(&cache_path).await
+        .map_err(|e| format!("Couldn't load cached market from {} \n{}", cache_path.to_string_lossy(), e))?;
+
+    let elapsed = Utc::now().signed_duration_since(cm.timestamp);
+    if elapsed.num_seconds() >= ttl.as_secs() as i64 {
+        progress.send_str(&format!("WARNING: Cached prices for {} are {} old.\n", market, format_age(elapsed))).await;
+    }
+
+    Ok(Some(cm.prices))
+}
+
 async fn load_cache(cache_path: &Path) -> Result<CachedMarket, String> {
     let file = fs::read(cache_path).await.map_err(|e| format!("Read sink failed! | {}", e))?;
     let read = serde_json::from_slice::<CachedMarket>(&file).map_err(|e| format!("Failed to deserialize! | {}", e))?;
     Ok(read)
 }
 
-async fn save_cache(cache_path: &Path, marketjson: Value) -> Result<(), String> {
+async fn save_cache(cache_path: &Path, marketjson: Value, timestamp: DateTime<Utc>) -> Result<(), String> {
     let cached = CachedMarket {
         prices: marketjson,
-        timestamp: Utc::now()
+        timestamp
     };
 
     dprintln!("{}", cache_path.display());
@@ -398,22 +596,62 @@ async fn save_cache(cache_path: &Path, marketjson: Value) -> Result<(), String>
 
 }
 
-async fn get_cached_market_data<'a, F, Fut>(cache_dir: &Path, iteminfo_provider: PricingProvider, market: Sites, fetch: F) -> Result<serde_json::Value, String>
+/// Uses the cached prices of `market` if they are younger than `ttl`, otherwise fetches and caches them.
+/// Reports the age of the cache either way. Fetched prices aren't cached in a dry run.
+#[allow(clippy::too_many_arguments)]
+async fn get_cached_market_data<S, P>(
+    price_source: &S,
+    market: Sites,
+    item_names: &[&str],
+    ttl: std::time::Duration,
+    force_refresh: bool,
+    dry_run: bool,
+    progress: &mut P
+) -> Result<serde_json::Value, String>
 where
-    F: Fn(Sites) -> Fut,
-    Fut: Future<Output = Result<serde_json::Value, String>>
+    S: PriceSource,
+    P: ProgressSink
 {
-    let cache_path = cache_dir.join( format!("{}_cache_{}.json", market.as_str(), iteminfo_provider.as_str().to_lowercase()) );
+    let cache_path = market_cache_path(market, price_source.name());
+    let ttl_display = format_age(chrono::TimeDelta::seconds(ttl.as_secs() as i64));
 
     if cache_path.exists() {
         match load_cache(&cache_path).await {
-            Ok(cm) => {
+            Ok(mut cm) => {
                 let elapsed = Utc::now().signed_duration_since(cm.timestamp);
-                if elapsed.num_seconds() < CACHE_TIME.as_secs() as i64 {
+
+                if elapsed.num_seconds() < ttl.as_secs() as i64 && !force_refresh {
+                    progress.send_str(
+                        &format!("\tUsing cached {} prices from {} ago (refreshed every {}).\n", market, format_age(elapsed), ttl_display)
+                    ).await;
+
+                    // Per item snapshots only know the items they were fetched for, so fetch the ones that are new since then
+                    if price_source.is_per_item() && let Some(cached) = cm.prices.as_object_mut() {
+                        let missing = item_names.iter()
+                            .filter(|n| !cached.contains_key(**n))
+                            .copied()
+                            .collect::<Vec<&str>>();
+
+                        if !missing.is_empty() {
+                            if let Value::Object(more) = price_source.get_market_data(market, &missing, progress).await? {
+                                cached.extend(more);
+                            }
+                            if !dry_run { save_cache(&cache_path, cm.prices.clone(), cm.timestamp).await?; }
+                        }
+                    }
                     Ok(cm.prices)
                 } else {
-                    let market_data = fetch(market).await?;
-                    save_cache(&cache_path, market_data.clone()).await?;
+                    progress.send_str(
+                        &format!(
+                            "\tFetching {} prices, {}.\n",
+                            market,
+                            if force_refresh { "forced refresh".to_string() }
+                            else { format!("cache was {} old (refreshed every {})", format_age(elapsed), ttl_display) }
+                        )
+                    ).await;
+
+                    let market_data = price_source.get_market_data(market, item_names, progress).await?;
+                    if !dry_run { save_cache(&cache_path, market_data.clone(), Utc::now()).await?; }
                     Ok(market_data)
                 }
             },
@@ -422,8 +660,10 @@ where
             },
         }
     } else {
-        let market_data = fetch(market).await?;
-        save_cache(&cache_path, market_data.clone()).await?;
+        progress.send_str(&format!("\tFetching {} prices, nothing cached yet.\n", market)).await;
+
+        let market_data = price_source.get_market_data(market, item_names, progress).await?;
+        if !dry_run { save_cache(&cache_path, market_data.clone(), Utc::now()).await?; }
         Ok(market_data)
     }
 }
@@ -453,6 +693,18 @@ pub fn clear_extra_iteminfo_given_quantity(
     }
 }
 
+/// Writes where the item is, which is "Inventory" for items not in a storage unit,
+/// and marks the manual items with their note. Manual items only have a location if they were added to an inventory row.
+#[inline]
+pub fn insert_item_origin(sheet: &mut Worksheet, excel: &SheetInfo, row_in_excel: usize, steamdata: &SteamData) {
+    if let Some(col_location) = &excel.col_location && (steamdata.manual_note.is_none() || steamdata.location.is_some()) {
+        insert_string_in_sheet(sheet, col_location, row_in_excel, steamdata.location.as_deref().unwrap_or("Inventory"));
+    }
+    if let Some(col_manual) = &excel.col_manual && let Some(note) = &steamdata.manual_note {
+        insert_string_in_sheet(sheet, col_manual, row_in_excel, if note.is_empty() { "Manual" } else { note.as_str() });
+    }
+}
+
 pub trait ToColumn {
     fn to_column(self) -> Option<u32>;
 }
@@ -536,3 +788,22 @@ impl IcedProgressSink {
         Self { sender: sender }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median(&[1.0]), 1.0);
+        assert_eq!(median(&[1.0, 2.0, 10.0]), 2.0);
+        assert_eq!(median(&[1.0, 2.0, 4.0, 10.0]), 3.0);
+    }
+
+    #[test]
+    fn only_trims_with_four_or_more_markets() {
+        assert_eq!(trim_outliers(&[1.0, 2.0, 3.0]), (&[1.0, 2.0, 3.0][..], false));
+        assert_eq!(trim_outliers(&[1.0, 2.0, 3.0, 100.0]), (&[2.0, 3.0][..], true));
+        assert_eq!(mean([2.0, 3.0].into_iter()), 2.5);
+    }
+}
@@ -1,3 +1,6 @@
+pub mod diff;
 pub mod excel_ops;
 pub mod excel_runtime;
-pub mod helpers;
\ No newline at end of file
+pub mod export;
+pub mod helpers;
+pub mod ods;
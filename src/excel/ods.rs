@@ -0,0 +1,179 @@
+use std::path::Path;
+
+use spreadsheet_ods::{read_ods, write_ods, Sheet, Value, WorkBook};
+use umya_spreadsheet::{helper::coordinate::index_from_coordinate, Spreadsheet, Worksheet};
+
+use crate::{excel::{excel_ops::write_atomically, helpers::ToColumn}, models::user_sheet::SheetInfo};
+
+pub fn is_ods(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("ods"))
+}
+
+/// What's in a cell, only what's needed to tell if the program changed it
+#[derive(Debug, PartialEq)]
+enum CellRepr {
+    Empty,
+    Number(f64),
+    Text(String),
+}
+
+impl CellRepr {
+    fn from_ods(value: &Value) -> Self {
+        match value {
+            Value::Number(n) | Value::Percentage(n) | Value::Currency(n, _) => CellRepr::Number(*n),
+            Value::Boolean(b) => CellRepr::Text( if *b { "TRUE" } else { "FALSE" }.to_string() ),
+            Value::Text(s) if !s.is_empty() => CellRepr::Text(s.clone()),
+            Value::DateTime(dt) => CellRepr::Text( dt.format("%d/%m/%Y %H:%M:%S").to_string() ),
+            // Rich text and durations are never written by the program, so they're just left alone
+            _ => CellRepr::Empty
+        }
+    }
+
+    fn from_umya(sheet: &Worksheet, col: u32, row: u32) -> Self {
+        let Some(cell) = sheet.get_cell((col, row)) else { return CellRepr::Empty };
+
+        if let Some(n) = cell.get_value_number() { return CellRepr::Number(n) }
+
+        let value = cell.get_value();
+        if value.is_empty() { CellRepr::Empty } else { CellRepr::Text(value.to_string()) }
+    }
+}
+
+/// Reads an ods workbook into a umya `Spreadsheet` so the rest of the program can use it like an xlsx.
+/// Only the values are copied (formulas as their last calculated value), formulas and styles stay in the ods file
+/// because `write_ods_managed` only writes the cells the program changed back into it.
+pub fn read_ods_as_book(path: &Path) -> Result<Spreadsheet, String> {
+    let workbook = read_ods(path).map_err(|e| format!("Failed to read file {}. \n{}", path.display(), e))?;
+
+    let mut book = umya_spreadsheet::new_file_empty_worksheet();
+
+    for i in 0..workbook.num_sheets() {
+        let ods_sheet = workbook.sheet(i);
+        let sheet = book.new_sheet(ods_sheet.name())
+            .map_err(|e| format!("Failed to create the sheet {}. {}", ods_sheet.name(), e))?;
+
+        let (rows, cols) = ods_sheet.used_grid_size();
+
+        for row in 0..rows {
+            for col in 0..cols {
+                match CellRepr::from_ods(ods_sheet.value(row, col)) {
+                    CellRepr::Number(n) => { sheet.get_cell_mut((col + 1, row + 1)).set_value_number(n); },
+                    CellRepr::Text(s) => { sheet.get_cell_mut((col + 1, row + 1)).set_value_string(s); },
+                    CellRepr::Empty => {}
+                }
+            }
+        }
+    }
+    Ok(book)
+}
+
+/// Cells in a sheet that the program writes to
+enum Managed {
+    /// Columns of the table from row_start_write_in_table and down, and the single cells (date, usd to x)
+    Table { cols: Vec<u32>, row_start: u32, cells: Vec<(u32, u32)> },
+    /// History, summary and realized gains sheets are written by the program from top to bottom
+    Everything
+}
+
+impl Managed {
+    fn table(excel: &SheetInfo) -> Self {
+        // col_sold is only read, so formulas in it are kept as is
+        let cols = [
+            Some(&excel.col_steam_name), Some(&excel.col_price), excel.col_previous_price.as_ref(), excel.col_price_change.as_ref(),
+            excel.col_price_change_pct.as_ref(), excel.col_net_price.as_ref(), excel.col_market.as_ref(), excel.col_gun_sticker_case.as_ref(), excel.col_skin_name.as_ref(),
+            excel.col_wear.as_ref(), excel.col_quantity.as_ref(), excel.col_asset_id.as_ref(), excel.col_inspect_link.as_ref(),
+            excel.col_csgoskins_link.as_ref(), excel.col_phase.as_ref(), excel.col_pattern.as_ref(), excel.col_float.as_ref(),
+            excel.col_location.as_ref(), excel.col_manual.as_ref(), excel.col_status.as_ref(),
+            excel.col_quantity_removed.as_ref(), excel.col_profit.as_ref(), excel.col_profit_pct.as_ref()
+        ]
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c.as_str().to_column())
+            .collect();
+
+        let cells = [&excel.rowcol_date, &excel.rowcol_usd_to_x]
+            .into_iter()
+            .flatten()
+            .filter_map(|cell| match index_from_coordinate(cell) {
+                (Some(col), Some(row), _, _) => Some((col, row)),
+                _ => None
+            })
+            .collect();
+
+        Managed::Table { cols, row_start: excel.row_start_write_in_table, cells }
+    }
+
+    /// Every (col, row) that could have been changed, 1-indexed like umya
+    fn cells(&self, highest_col: u32, highest_row: u32) -> Vec<(u32, u32)> {
+        match self {
+            Managed::Table { cols, row_start, cells } => cols.iter()
+                .flat_map(|col| (*row_start..=highest_row).map(move |row| (*col, row)))
+                .chain(cells.iter().copied())
+                .collect(),
+            Managed::Everything => (1..=highest_col)
+                .flat_map(|col| (1..=highest_row).map(move |row| (col, row)))
+                .collect()
+        }
+    }
+}
+
+/// Writes the cells the program manages from `book` into the ods file at `path`, leaving everything else
+/// (formulas, styles and the columns that aren't in `SheetInfo`) the way it was.
+///
+/// `account_sheets` are the sheets with the item table, where None is the first sheet.
+pub fn write_ods_managed(
+    path: &Path,
+    book: &Spreadsheet,
+    excel: &SheetInfo,
+    account_sheets: &[Option<String>]
+) -> Result<(), String> {
+    let mut workbook: WorkBook = read_ods(path).map_err(|e| format!("Failed to read file {}. \n{}", path.display(), e))?;
+
+    let table = Managed::table(excel);
+    let everything = Managed::Everything;
+
+    let mut sheets: Vec<(&Worksheet, &Managed)> = Vec::new();
+
+    for sheet_name in account_sheets {
+        let sheet = match sheet_name {
+            Some(sn) => book.get_sheet_by_name(sn),
+            None => book.get_sheet(&0)
+        }.ok_or_else(|| format!("Failed to get the sheet {}.", sheet_name.as_deref().unwrap_or("Sheet1")))?;
+
+        sheets.push((sheet, &table));
+    }
+    for sheet_name in [&excel.history_sheet_name, &excel.summary_sheet_name, &excel.realized_sheet_name].into_iter().flatten() {
+        if let Some(sheet) = book.get_sheet_by_name(sheet_name) { sheets.push((sheet, &everything)); }
+    }
+
+    for (sheet, managed) in sheets {
+        let index = match (0..workbook.num_sheets()).find(|i| workbook.sheet(*i).name() == sheet.get_name()) {
+            Some(i) => i,
+            None => {
+                workbook.push_sheet(Sheet::new(sheet.get_name()));
+                workbook.num_sheets() - 1
+            }
+        };
+        let ods_sheet = workbook.sheet_mut(index);
+
+        // Rows removed from the summary sheet are still in the ods file, so the area covered is the biggest of the two
+        let (ods_rows, ods_cols) = ods_sheet.used_grid_size();
+        let (umya_cols, umya_rows) = sheet.get_highest_column_and_row();
+
+        for (col, row) in managed.cells(umya_cols.max(ods_cols), umya_rows.max(ods_rows)) {
+            let new = CellRepr::from_umya(sheet, col, row);
+            if CellRepr::from_ods(ods_sheet.value(row - 1, col - 1)) == new { continue }
+
+            // A formula would calculate over the value written, so it's replaced
+            if ods_sheet.formula(row - 1, col - 1).is_some() { ods_sheet.remove_cell(row - 1, col - 1); }
+
+            match new {
+                CellRepr::Number(n) => ods_sheet.set_value(row - 1, col - 1, n),
+                CellRepr::Text(s) => ods_sheet.set_value(row - 1, col - 1, s),
+                CellRepr::Empty => ods_sheet.set_value(row - 1, col - 1, Value::Empty),
+            }
+        }
+    }
+
+    write_atomically(path, |tmp| write_ods(&mut workbook, tmp).map_err(|e| format!("{}", e)))
+}
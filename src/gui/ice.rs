@@ -1,6 +1,6 @@
 use std::{fs::File, path::PathBuf, str::FromStr};
 
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use strum::IntoEnumIterator;
 use rfd::AsyncFileDialog;
 
@@ -14,13 +14,14 @@ use iced::{Element, Length, Pixels, Size, Subscription, Task, window};
 use crate::parsing;
 use crate::{dprintln, parsing::sanitizing};
 use crate::excel::{excel_runtime, helpers::Progress};
+use crate::storage::backup::{self, DEFAULT_BACKUP_COUNT};
 use crate::gui::templates_n_methods::{
-    btn_base, checkbox_default, editor_paste, path_to_file_name, pick_list_template, slider_template, task_cell_if_english_alphabetic, task_col_if_english_alphabetic, text_editor_template, text_input_template,
+    btn_base, checkbox_default, editor_paste, parse_market_values, path_to_file_name, pick_list_template, slider_template, task_cell_if_english_alphabetic, task_col_if_english_alphabetic, text_editor_template, text_input_template,
     ToNumeric, ToOption
 };
 use crate::models::{
     price::{Currencies, PricingMode, PricingProvider},
-    user_sheet::{SheetInfo, UserInfo, UserSheet},
+    user_sheet::{QuantityDecrease, SheetInfo, UserInfo, UserSheet},
     web::{ItemInfoProvider, Sites}
 };
 
@@ -48,6 +49,7 @@ pub enum Exec {
     IteminfoProvider(ItemInfoProvider),
     UsdToX(Currencies),
     PricingMode(PricingMode),
+    QuantityDecrease(QuantityDecrease),
     PricingProvider(PricingProvider),
 
     PauseTimeMs(u16),
@@ -57,11 +59,22 @@ pub enum Exec {
 
     Steamid(String),
     SheetName(String),
+    HistorySheetName(String),
+    SummarySheetName(String),
+    RealizedSheetName(String),
 
     IgnoreAlreadySold(bool),
     GroupSimularItems(bool),
     FetchPrices(bool),
     FetchSteam(bool),
+    Offline(bool),
+    ForceRefresh(bool),
+    DryRun(bool),
+    Reconcile(bool),
+    ExportCsv(bool),
+    ExportJsonl(bool),
+    CacheTtl(text_editor::Action),
+    MarketFees(text_editor::Action),
     OnlyShowRuntimeResult,
     IgnoreSteamNames(text_editor::Action),
 
@@ -72,6 +85,10 @@ pub enum Exec {
     // Columns
     ColSteamName(String),
     ColPrice(String),
+    ColPreviousPrice(String),
+    ColPriceChange(String),
+    ColPriceChangePct(String),
+    ColNetPrice(String),
     ColGunStickerCase(String),
     ColSkinName(String),
     ColWear(String),
@@ -79,11 +96,22 @@ pub enum Exec {
     ColPattern(String),
     ColPhase(String),
     ColQuantity(String),
+    ColQuantityRemoved(String),
+    ColBuyPrice(String),
+    ColBuyDate(String),
+    ColBuyCurrency(String),
+    ColProfit(String),
+    ColProfitPct(String),
     ColMarket(String),
     ColSold(String),
+    ColSoldDate(String),
+    ColSoldFee(String),
     ColInspectLink(String),
     ColCsgoskinsLink(String),
     ColAssetId(String),
+    ColLocation(String),
+    ColManual(String),
+    ColStatus(String),
 
     // Cell
     CellDate(String),
@@ -91,6 +119,10 @@ pub enum Exec {
 
     BeginPathToSheet,
     FinishPathToSheet(Option<PathBuf>),
+    BeginStorageUnitsPath,
+    FinishStorageUnitsPath(Option<PathBuf>),
+    BeginManualItemsPath,
+    FinishManualItemsPath(Option<PathBuf>),
     BeginLoadData,
     FinishLoadData(Option<PathBuf>),
     BeginSaveData,
@@ -101,6 +133,8 @@ pub enum Exec {
     RuntimeResult(text_editor::Action),
 
     WindowResized(Size),
+    BeginRestoreBackup,
+    FinishRestoreBackup(Result<String, String>),
     BeginOpenUrl(&'static str),
     FinishOpenUrl(Result<(), String>),
     //Exit,
@@ -115,13 +149,16 @@ pub struct App {
     text_percent_threshold: String,
     editor_ignore_steam_names: text_editor::Content,
     editor_prefer_markets: text_editor::Content,
+    editor_cache_ttl: text_editor::Content,
+    editor_market_fees: text_editor::Content,
     editor_runtime_result: text_editor::Content,
     text_input_steamid: String,
     text_input_row_start_write_in_table: String,
     text_input_row_stop_write_in_table: String,
     pick_list_usd_to_x: Vec<Currencies>,
     pick_list_pricing_provider: [PricingProvider; 2],
-    pick_list_pricing_mode: [PricingMode; 4],
+    pick_list_pricing_mode: [PricingMode; 7],
+    pick_list_quantity_decrease: [QuantityDecrease; 3],
     pick_list_iteminfo_provider: [ItemInfoProvider; 3],
     is_file_dialog_open: bool,
     is_excel_running: bool,
@@ -153,18 +190,38 @@ impl Default for App {
                     group_simular_items:        false,
                     fetch_prices:               true,
                     fetch_steam:                true,
+                    offline:                    false,
+                    force_refresh:              false,
+                    cache_ttl_minutes:          IndexMap::new(),
+                    market_fees:                IndexMap::new(),
+                    dry_run:                    false,
+                    reconcile:                  false,
+                    quantity_decrease:          QuantityDecrease::Never,
+                    backup_count:               DEFAULT_BACKUP_COUNT,
+                    export_csv:                 false,
+                    export_jsonl:               false,
+                    storage_units_path:         None,
+                    manual_items_path:          None,
+                    accounts:                   Vec::new(),
 
                     ingore_steam_names:         None
                 },
                 sheet: SheetInfo {
                     path_to_sheet:              None,
                     sheet_name:                 None,
+                    history_sheet_name:         None,
+                    summary_sheet_name:         None,
+                    realized_sheet_name:        None,
 
                     row_start_write_in_table:   1,
                     row_stop_write_in_table:    None,
 
                     col_steam_name:             String::from(""),
                     col_price:                  String::from(""),
+                    col_previous_price:         None,
+                    col_price_change:           None,
+                    col_price_change_pct:       None,
+                    col_net_price:              None,
 
                     col_gun_sticker_case:       None,
                     col_skin_name:              None,
@@ -173,11 +230,22 @@ impl Default for App {
                     col_pattern:                None,
                     col_phase:                  None,
                     col_quantity:               None,
+                    col_quantity_removed:       None,
+                    col_buy_price:              None,
+                    col_buy_date:               None,
+                    col_buy_currency:           None,
+                    col_profit:                 None,
+                    col_profit_pct:             None,
                     col_market:                 None,
                     col_sold:                   None,
+                    col_sold_date:              None,
+                    col_sold_fee:               None,
                     col_inspect_link:           None,
                     col_csgoskins_link:         None,
                     col_asset_id:               None,
+                    col_location:               None,
+                    col_manual:                 None,
+                    col_status:                 None,
                     rowcol_date:                None,
                     rowcol_usd_to_x:            None,
                 }
@@ -189,6 +257,8 @@ impl Default for App {
             only_show_runtime_result: false,
             editor_ignore_steam_names: text_editor::Content::new(),
             editor_prefer_markets: text_editor::Content::new(),
+            editor_cache_ttl: text_editor::Content::new(),
+            editor_market_fees: text_editor::Content::new(),
             editor_runtime_result: Content::with_text( ADDITIONAL_INFO ),
             text_pause_time_ms: String::new(),
             text_percent_threshold: String::new(),
@@ -198,7 +268,8 @@ impl Default for App {
             window_size: Size::default(),
 
             pick_list_pricing_provider: [PricingProvider::Csgoskins, PricingProvider::Csgotrader],
-            pick_list_pricing_mode: [PricingMode::Cheapest, PricingMode::Hierarchical, PricingMode::MostExpensive, PricingMode::Random],
+            pick_list_pricing_mode: [PricingMode::Cheapest, PricingMode::Hierarchical, PricingMode::MostExpensive, PricingMode::Random, PricingMode::BestNet, PricingMode::Median, PricingMode::Average],
+            pick_list_quantity_decrease: [QuantityDecrease::Never, QuantityDecrease::Always, QuantityDecrease::Record],
             pick_list_iteminfo_provider: [ItemInfoProvider::Csfloat, ItemInfoProvider::Csgotrader, ItemInfoProvider::Steam],
             pick_list_usd_to_x: {
                 let mut curr = Currencies::iter().filter(|c| *c != Currencies::None).collect::<Vec<Currencies>>();
@@ -214,7 +285,7 @@ impl Default for App {
 
 impl App {
     fn update(state: &mut Self, exec: Exec) -> Task<Exec> {
-        if state.is_file_dialog_open && !matches!( exec, Exec::FinishLoadData(_) | Exec::FinishSaveData(_) /*| Exec::Exit */| Exec::FinishPathToSheet(_)) { return Task::none() }
+        if state.is_file_dialog_open && !matches!( exec, Exec::FinishLoadData(_) | Exec::FinishSaveData(_) /*| Exec::Exit */| Exec::FinishPathToSheet(_) | Exec::FinishStorageUnitsPath(_) | Exec::FinishManualItemsPath(_)) { return Task::none() }
         if state.is_excel_running && !matches!(exec, Exec::UpdateRun(_) | Exec::FinishRun(_) | Exec::BeginOpenUrl(_)) { return Task::none() }
 
         let user =           &mut state.usersheet.user;
@@ -228,10 +299,17 @@ impl App {
             Exec::GroupSimularItems(b)  => { user.group_simular_items = b; Task::none() }
             Exec::FetchPrices(b)        => { user.fetch_prices = b; Task::none() }
             Exec::FetchSteam(b)         => { user.fetch_steam = b; Task::none() }
+            Exec::Offline(b)            => { user.offline = b; Task::none() }
+            Exec::ForceRefresh(b)       => { user.force_refresh = b; Task::none() }
+            Exec::DryRun(b)             => { user.dry_run = b; Task::none() }
+            Exec::Reconcile(b)          => { user.reconcile = b; Task::none() }
+            Exec::ExportCsv(b)          => { user.export_csv = b; Task::none() }
+            Exec::ExportJsonl(b)        => { user.export_jsonl = b; Task::none() }
             Exec::OnlyShowRuntimeResult => { state.only_show_runtime_result = !state.only_show_runtime_result; Task::none() }
             Exec::UsdToX(c)             => { user.usd_to_x = c; Task::none() }
             Exec::PricingProvider(pp)   => { user.pricing_provider = pp; Task::none() }
             Exec::PricingMode(pm)       => { user.pricing_mode = pm; Task::none() }
+            Exec::QuantityDecrease(qd)  => { user.quantity_decrease = qd; Task::none() }
             Exec::IteminfoProvider(ip)  => { user.iteminfo_provider = ip; Task::none() }
             Exec::IgnoreSteamNames(act) => {
                 state.editor_ignore_steam_names.perform( act.clone() );
@@ -250,6 +328,8 @@ impl App {
                 Task::none()
             },
             Exec::PreferMarkets(act) => {
+                state.editor_prefer_markets.perform( act.clone() );
+
                 if matches!(act, text_editor::Action::Edit(_)) {
                     user.prefer_markets = if !state.editor_prefer_markets.text().is_empty() {
                         Some( {
@@ -262,7 +342,23 @@ impl App {
                         } )
                     } else { None };
                 };
-                state.editor_prefer_markets.perform(act);
+                Task::none()
+            },
+            Exec::CacheTtl(act) => {
+                // Edit first so what's saved is the same as the text in the editor
+                state.editor_cache_ttl.perform( act.clone() );
+
+                if matches!(act, text_editor::Action::Edit(_)) {
+                    user.cache_ttl_minutes = parse_market_values(&state.editor_cache_ttl.text(), |minutes| minutes.parse::<u32>().ok());
+                };
+                Task::none()
+            },
+            Exec::MarketFees(act) => {
+                state.editor_market_fees.perform( act.clone() );
+
+                if matches!(act, text_editor::Action::Edit(_)) {
+                    user.market_fees = parse_market_values(&state.editor_market_fees.text(), |fee| fee.trim_end_matches('%').trim().parse::<f64>().ok());
+                };
                 Task::none()
             },
             Exec::RuntimeResult(act) => {
@@ -331,8 +427,15 @@ impl App {
                 Task::none()
             }
             Exec::SheetName(sn) =>          { sheet.sheet_name = sn.to_option(); Task::none() }
+            Exec::HistorySheetName(sn) =>   { sheet.history_sheet_name = sn.to_option(); Task::none() }
+            Exec::SummarySheetName(sn) =>   { sheet.summary_sheet_name = sn.to_option(); Task::none() }
+            Exec::RealizedSheetName(sn) =>  { sheet.realized_sheet_name = sn.to_option(); Task::none() }
             Exec::ColSteamName(s) =>        { task_col_if_english_alphabetic(&mut sheet.col_steam_name, &s) }
             Exec::ColPrice(s) =>            { task_col_if_english_alphabetic(&mut sheet.col_price, &s) }
+            Exec::ColPreviousPrice(s) =>    { task_col_if_english_alphabetic(&mut sheet.col_previous_price, &s) }
+            Exec::ColPriceChange(s) =>      { task_col_if_english_alphabetic(&mut sheet.col_price_change, &s) }
+            Exec::ColPriceChangePct(s) =>   { task_col_if_english_alphabetic(&mut sheet.col_price_change_pct, &s) }
+            Exec::ColNetPrice(s) =>         { task_col_if_english_alphabetic(&mut sheet.col_net_price, &s) }
             Exec::ColGunStickerCase(gsc) => { task_col_if_english_alphabetic(&mut sheet.col_gun_sticker_case, &gsc) }
             Exec::ColSkinName(s) =>         { task_col_if_english_alphabetic(&mut sheet.col_skin_name, &s) }
             Exec::ColWear(s) =>             { task_col_if_english_alphabetic(&mut sheet.col_wear, &s) }
@@ -340,11 +443,22 @@ impl App {
             Exec::ColPattern(s) =>          { task_col_if_english_alphabetic(&mut sheet.col_pattern, &s) }
             Exec::ColPhase(s) =>            { task_col_if_english_alphabetic(&mut sheet.col_phase, &s) }
             Exec::ColQuantity(s) =>         { task_col_if_english_alphabetic(&mut sheet.col_quantity, &s) }
+            Exec::ColQuantityRemoved(s) =>  { task_col_if_english_alphabetic(&mut sheet.col_quantity_removed, &s) }
+            Exec::ColBuyPrice(s) =>         { task_col_if_english_alphabetic(&mut sheet.col_buy_price, &s) }
+            Exec::ColBuyDate(s) =>          { task_col_if_english_alphabetic(&mut sheet.col_buy_date, &s) }
+            Exec::ColBuyCurrency(s) =>      { task_col_if_english_alphabetic(&mut sheet.col_buy_currency, &s) }
+            Exec::ColProfit(s) =>           { task_col_if_english_alphabetic(&mut sheet.col_profit, &s) }
+            Exec::ColProfitPct(s) =>        { task_col_if_english_alphabetic(&mut sheet.col_profit_pct, &s) }
             Exec::ColMarket(s) =>           { task_col_if_english_alphabetic(&mut sheet.col_market, &s) }
             Exec::ColSold(s) =>             { task_col_if_english_alphabetic(&mut sheet.col_sold, &s) }
+            Exec::ColSoldDate(s) =>         { task_col_if_english_alphabetic(&mut sheet.col_sold_date, &s) }
+            Exec::ColSoldFee(s) =>          { task_col_if_english_alphabetic(&mut sheet.col_sold_fee, &s) }
             Exec::ColInspectLink(s) =>      { task_col_if_english_alphabetic(&mut sheet.col_inspect_link, &s) }
             Exec::ColCsgoskinsLink(s) =>    { task_col_if_english_alphabetic(&mut sheet.col_csgoskins_link, &s) }
             Exec::ColAssetId(s) =>          { task_col_if_english_alphabetic(&mut sheet.col_asset_id, &s) }
+            Exec::ColLocation(s) =>         { task_col_if_english_alphabetic(&mut sheet.col_location, &s) }
+            Exec::ColManual(s) =>           { task_col_if_english_alphabetic(&mut sheet.col_manual, &s) }
+            Exec::ColStatus(s) =>           { task_col_if_english_alphabetic(&mut sheet.col_status, &s) }
             Exec::CellDate(s) =>            { task_cell_if_english_alphabetic(&mut sheet.rowcol_date, &s) }
             Exec::CellUsdToX(s) =>          { task_cell_if_english_alphabetic(&mut sheet.rowcol_usd_to_x, &s) }
 
@@ -356,8 +470,8 @@ impl App {
                     async {
                         let save_file = AsyncFileDialog::new()
                             .set_directory( std::env::current_dir().unwrap_or(std::env::home_dir().expect("what")) )
-                            .add_filter("EXCEL files", &["xlsx"])
-                            .set_title("Get xlsx file")
+                            .add_filter("Spreadsheets", &["xlsx", "ods"])
+                            .set_title("Get xlsx or ods file")
                             .pick_file()
                             .await;
                         save_file.map(|f| f.inner().to_path_buf() )
@@ -373,6 +487,51 @@ impl App {
                 }
                 Task::none()
             },
+            Exec::BeginStorageUnitsPath => {
+                state.is_file_dialog_open = true;
+
+                Task::perform(
+                    async {
+                        let file = AsyncFileDialog::new()
+                            .set_directory( std::env::current_dir().unwrap_or(std::env::home_dir().expect("what")) )
+                            .add_filter("JSON Files", &["json"])
+                            .set_title("Get storage unit JSON file")
+                            .pick_file()
+                            .await;
+                        file.map(|f| f.inner().to_path_buf() )
+                    },
+                    Exec::FinishStorageUnitsPath
+                )
+            }
+            Exec::FinishStorageUnitsPath(file) => {
+                state.is_file_dialog_open = false;
+
+                // Cancelling the dialog removes the file, so storage units can be turned off again
+                user.storage_units_path = file;
+                Task::none()
+            },
+            Exec::BeginManualItemsPath => {
+                state.is_file_dialog_open = true;
+
+                Task::perform(
+                    async {
+                        let file = AsyncFileDialog::new()
+                            .set_directory( std::env::current_dir().unwrap_or(std::env::home_dir().expect("what")) )
+                            .add_filter("JSON Files", &["json"])
+                            .set_title("Get manual items JSON file")
+                            .pick_file()
+                            .await;
+                        file.map(|f| f.inner().to_path_buf() )
+                    },
+                    Exec::FinishManualItemsPath
+                )
+            }
+            Exec::FinishManualItemsPath(file) => {
+                state.is_file_dialog_open = false;
+
+                user.manual_items_path = file;
+                Task::none()
+            },
             Exec::BeginSaveData => {
                 state.is_file_dialog_open = true;
 
@@ -442,6 +601,12 @@ impl App {
                         state.saved_data = Ok(None);
                         state.editor_ignore_steam_names = text_editor::Content::with_text( &isn_input );
                         state.editor_prefer_markets = text_editor::Content::with_text( &pm_input );
+                        state.editor_cache_ttl = text_editor::Content::with_text(
+                            &user.cache_ttl_minutes.iter().map(|(m, t)| format!("{}={}", m, t)).collect::<Vec<String>>().join(", ")
+                        );
+                        state.editor_market_fees = text_editor::Content::with_text(
+                            &user.market_fees.iter().map(|(m, f)| format!("{}={}", m, f)).collect::<Vec<String>>().join(", ")
+                        );
                         state.text_pause_time_ms = user.pause_time_ms.to_string();
                         state.text_percent_threshold = user.percent_threshold.to_string();
                         state.text_input_steamid = user.steamid.to_string();
@@ -500,6 +665,27 @@ impl App {
                 state.is_excel_running = false;
                 Task::none()
             },
+            Exec::BeginRestoreBackup => {
+                let Some(path) = sheet.path_to_sheet.clone() else {
+                    state.editor_runtime_result.perform( editor_paste("\nError!\nNo spreadsheet to restore, set the path to sheet first.") );
+                    return Task::none()
+                };
+
+                Task::perform(
+                    async move {
+                        backup::restore_last_backup(&path)
+                            .map(|restored| format!("Restored {} from {}", path.display(), restored.display()))
+                    },
+                    Exec::FinishRestoreBackup
+                )
+            },
+            Exec::FinishRestoreBackup(res) => {
+                match res {
+                    Ok(msg) => state.editor_runtime_result.perform( editor_paste(&format!("\n{}\n", msg)) ),
+                    Err(e) => state.editor_runtime_result.perform( editor_paste(&format!("\nError!\n{}", e)) ),
+                }
+                Task::none()
+            },
             Exec::BeginOpenUrl(s) => {
                 Task::perform(
                     async move { open::that(s).map_err(|_| String::from("Failed to open URL")) },
@@ -584,8 +770,15 @@ impl App {
                     user.fetch_steam,
                     (300.0, 100.0),
                     Exec::FetchSteam
+                ),
+                checkbox_default(
+                    "Offline?",
+                    "Use the inventory, prices and exchange rates cached from the last run instead of fetching them, no matter how old they are.",
+                    user.offline,
+                    (300.0, 100.0),
+                    Exec::Offline
                 )
-            ].spacing( state.window_size.width / 4.0 - 180.0 )
+            ].spacing( state.window_size.width / 5.0 - 170.0 )
         ).center_x( Length::Fill ).width( Length::Fill );
 
         content = content.push( radio_buttons );
@@ -615,7 +808,7 @@ impl App {
         let pricing_provider = if !user.fetch_prices { column![] }
         else {
             pick_list_template(
-                "Which site/API that fetches the prices. \nPS: Only CsgoTrader implemented.",
+                "Which site/API that fetches the prices. \nCsgoTrader fetches every market at once. Csgoskins fetches the page of every item one by one using the pause time, so it is a lot slower.",
                 "Pricing provider",
                 Some( user.pricing_provider ),
                 &state.pick_list_pricing_provider,
@@ -626,20 +819,100 @@ impl App {
             )
         };
 
+        let force_refresh = checkbox_default(
+            "Force refresh?",
+            "Fetch the prices of every market even if the cached prices are still valid.",
+            user.force_refresh,
+            (300.0, 100.0),
+            Exec::ForceRefresh
+        );
+
+        let dry_run = checkbox_default(
+            "Dry run?",
+            "Do everything except writing. Shows every cell that would change (sheet, row, column, old and new value) in the output instead.",
+            user.dry_run,
+            (300.0, 100.0),
+            Exec::DryRun
+        );
+
+        let reconcile = checkbox_default(
+            "Mark missing items?",
+            "Mark the rows that aren't in your inventory anymore, in the status column if set, otherwise by striking through the name.",
+            user.reconcile,
+            (300.0, 100.0),
+            Exec::Reconcile
+        );
+
+        let col_status = if !user.reconcile || !user.fetch_steam { column![] }
+        else {
+            text_input_template(
+                "Name of column where it's written if the item is still in your inventory ('In inventory') or not ('Missing'). If not set, missing items are struck through instead.",
+                (300.0, 100.0),
+                "Col status?",
+                "Ex: Q",
+                sheet.col_status.as_ref(),
+                Exec::ColStatus,
+                FILL
+            )
+        };
+
+        let export_csv = checkbox_default(
+            "Export CSV?",
+            "Write every row with its price, market, float, pattern and phase to a csv file next to the spreadsheet after every run.",
+            user.export_csv,
+            (300.0, 100.0),
+            Exec::ExportCsv
+        );
+
+        let export_jsonl = checkbox_default(
+            "Export JSON Lines?",
+            "Same as Export CSV, but a .jsonl file with one JSON object per row.",
+            user.export_jsonl,
+            (300.0, 100.0),
+            Exec::ExportJsonl
+        );
+
         let pricing_mode = if !user.fetch_prices { column![] }
         else {
             pick_list_template(
-                "Chooses how the price of your items are calculated if you have chosen multiple preferred markets.",
+                "Chooses how the price of your items are calculated if you have chosen multiple preferred markets.\nMedian and Average (without the cheapest and most expensive market when there are 4 or more) write which markets were used in the market column.",
                 "Pricing mode",
                 Some( user.pricing_mode ),
                 &state.pick_list_pricing_mode,
                 PricingMode::to_string,
                 Exec::PricingMode,
+                (400.0, 100.0),
+                FILL
+            )
+        };
+
+        let quantity_decrease = if !user.group_simular_items || !user.fetch_steam { column![] }
+        else {
+            pick_list_template(
+                "What to do when you have less of an item in your inventory than in the spreadsheet. \nNever only raises quantities, Always lowers them to match Steam and Record also adds how many left to the quantity removed column.",
+                "Quantity decrease",
+                Some( user.quantity_decrease ),
+                &state.pick_list_quantity_decrease,
+                QuantityDecrease::to_string,
+                Exec::QuantityDecrease,
                 (400.0, 75.0),
                 FILL
             )
         };
 
+        let col_quantity_removed = if !user.group_simular_items || user.quantity_decrease != QuantityDecrease::Record { column![] }
+        else {
+            text_input_template(
+                "Name of column where the total amount of the item that has left your inventory is written.",
+                (300.0, 100.0),
+                "Col quantity removed?",
+                "Ex: R",
+                sheet.col_quantity_removed.as_ref(),
+                Exec::ColQuantityRemoved,
+                FILL
+            )
+        };
+
         let iteminfo_provider = pick_list_template(
             "Which site/API fetches the additional info about your items like float, pattern etc... \nOnly CSFloat and Steam implemented. Use Steam 99% of the time, but if you have a doppler knife/gun in your inventory, use csfloat to get accurate pricing.",
             "Iteminfo provider",
@@ -696,10 +969,10 @@ impl App {
         );
 
         // Sliders and text editors ------------------------------
-        let pause_time_ms = if user.iteminfo_provider == ItemInfoProvider::Steam { column![] }
+        let pause_time_ms = if user.iteminfo_provider == ItemInfoProvider::Steam && user.pricing_provider != PricingProvider::Csgoskins { column![] }
         else {
             slider_template(
-                "If you fetch additional iteminfo, this is the time between each fetch.",
+                "If you fetch additional iteminfo or use Csgoskins as pricing provider, this is the time between each fetch.",
                 "Pause time (in ms)",
                 (300.0, 100.0),
                 1000..=2500,
@@ -733,6 +1006,32 @@ impl App {
                 Exec::PreferMarkets
             )
         };
+        let cache_ttl = if !user.fetch_prices { column![] }
+        else {
+            text_editor_template(
+                "How many minutes the prices of a market are cached before they are fetched again. Markets not written here are cached for 6 hours. \nEx: Steam=60, Buff163=360",
+                "Cache Time Per Market?",
+                "(Market=Minutes Seperated By ',')",
+                &state.editor_cache_ttl,
+                100,
+                FILL,
+                (400.0, 125.0),
+                Exec::CacheTtl
+            )
+        };
+        let market_fees = if !user.fetch_prices { column![] }
+        else {
+            text_editor_template(
+                "How many percent of the sale price a market keeps when you sell, used for the net price column and the Best Net pricing mode. \nMarkets not written here use a rough default (Steam 13, Skinport 8, CSMoney 5, Bitskins 5, Buff163 2.5, CSFloat 2, Youpin 1). \nEx: Steam=13, Buff163=2.5",
+                "Fee Per Market?",
+                "(Market=Percent Seperated By ',')",
+                &state.editor_market_fees,
+                100,
+                FILL,
+                (400.0, 150.0),
+                Exec::MarketFees
+            )
+        };
         let percent_threshold = if matches!(user.pricing_mode, PricingMode::Hierarchical) && user.fetch_prices {
             slider_template(
                 "When Pricing Mode is Hierarchical, this sets the minimum percent price difference required to switch to a lower-ranked market. The program selects the cheapest market only if its price is at least this much lower than the previous one.",
@@ -748,7 +1047,7 @@ impl App {
 
         // Misc
         let sheet_name = text_input_template(
-            "Name of the sheet in your xlsx file that you want to alter. Default name in new spreadsheet is Sheet1.",
+            "Name of the sheet in your xlsx/ods file that you want to alter. Default name in new spreadsheet is Sheet1.",
             (300.0, 100.0),
             if user.fetch_steam {"Sheet name?"} else {"Sheet name"},
             "Ex: Sheet1",
@@ -757,6 +1056,32 @@ impl App {
             FILL
         );
 
+        let history_sheet_name = if !user.fetch_prices { column![] }
+        else {
+            text_input_template(
+                "Name of the sheet where a row with the date, total value, item count, currency and exchange rate is added every run. Created if it doesn't exist.",
+                (300.0, 100.0),
+                "History sheet name?",
+                "Ex: History",
+                sheet.history_sheet_name.as_ref(),
+                Exec::HistorySheetName,
+                FILL
+            )
+        };
+
+        let summary_sheet_name = if !user.fetch_prices { column![] }
+        else {
+            text_input_template(
+                "Name of the sheet where the total value of every account is written. Accounts are set in the savefile under 'accounts' (steamid, steamloginsecure and sheet_name).",
+                (300.0, 100.0),
+                "Summary sheet name?",
+                "Ex: Summary",
+                sheet.summary_sheet_name.as_ref(),
+                Exec::SummarySheetName,
+                FILL
+            )
+        };
+
         // Cols
         let col_full_name = text_input_template(
             "Name of column where the name of the item IN FULL is put (Ex: AK-47 | Blue Laminate (Field-Tested). This is needed to index the spreadsheet.",
@@ -779,6 +1104,54 @@ impl App {
                 FILL
             )
         };
+        let col_previous_price = if !user.fetch_prices { column![] }
+        else {
+            text_input_template(
+                "Name of column where the price from the last run can be written before the new price overwrites it.",
+                (300.0, 100.0),
+                "Col previous price?",
+                "Ex: Q",
+                sheet.col_previous_price.as_ref(),
+                Exec::ColPreviousPrice,
+                FILL
+            )
+        };
+        let col_price_change = if !user.fetch_prices { column![] }
+        else {
+            text_input_template(
+                "Name of column where the change in price since the last run can be written.",
+                (300.0, 100.0),
+                "Col price change?",
+                "Ex: R",
+                sheet.col_price_change.as_ref(),
+                Exec::ColPriceChange,
+                FILL
+            )
+        };
+        let col_price_change_pct = if !user.fetch_prices { column![] }
+        else {
+            text_input_template(
+                "Name of column where the change in price since the last run can be written in percent (Ex: 5.2 for +5.2%).",
+                (300.0, 100.0),
+                "Col price change %?",
+                "Ex: S",
+                sheet.col_price_change_pct.as_ref(),
+                Exec::ColPriceChangePct,
+                FILL
+            )
+        };
+        let col_net_price = if !user.fetch_prices { column![] }
+        else {
+            text_input_template(
+                "Name of column where the price after the fee of the market it's from can be written. Fees are set in Fee Per Market.\nWith Median and Average it's the median or average of the price after the fee on each market used.",
+                (300.0, 100.0),
+                "Col net price?",
+                "Ex: X",
+                sheet.col_net_price.as_ref(),
+                Exec::ColNetPrice,
+                FILL
+            )
+        };
         let col_gun_sticker_case = text_input_template(
             "Name of column where the gun name can be written (Ex: M4A4)",
             (300.0, 100.0),
@@ -878,6 +1251,42 @@ impl App {
                 FILL
             )
         };
+        let col_sold_date = if !user.ignore_already_sold { column![] }
+        else {
+            text_input_template(
+                "Name of column where you write when you sold the item. Only read by the program, used for the realized gains sheet.",
+                (300.0, 100.0),
+                "Col sold date?",
+                "Ex: V",
+                sheet.col_sold_date.as_ref(),
+                Exec::ColSoldDate,
+                FILL
+            )
+        };
+        let col_sold_fee = if !user.ignore_already_sold { column![] }
+        else {
+            text_input_template(
+                "Name of column where you write the fee paid per item when you sold it. Only read by the program, used for the realized gains sheet.",
+                (300.0, 100.0),
+                "Col sold fee?",
+                "Ex: W",
+                sheet.col_sold_fee.as_ref(),
+                Exec::ColSoldFee,
+                FILL
+            )
+        };
+        let realized_sheet_name = if !user.ignore_already_sold { column![] }
+        else {
+            text_input_template(
+                "Name of the sheet where the profit of every sold row is written, (sold - fee - buy price) * quantity, with totals per month.\nThe sold column is the price per item it was sold for.",
+                (300.0, 100.0),
+                "Realized gains sheet name?",
+                "Ex: Realized",
+                sheet.realized_sheet_name.as_ref(),
+                Exec::RealizedSheetName,
+                FILL
+            )
+        };
         let col_inspect_link = text_input_template(
             "Name of column where the inspect link for the items can be written and read \n(Ex: steam://rungame/730/76561202255233023/+csgo_econ_action_preview%20S76561198389123475A34543022281D9279926981479153949)",
             (700.0, 100.0),
@@ -911,6 +1320,82 @@ impl App {
             )
         };
 
+        let col_location = text_input_template(
+            "Name of column where it can be written if the item is in the inventory or in which storage unit(s) it is.",
+            (300.0, 100.0),
+            "Col location?",
+            "Ex: O",
+            sheet.col_location.as_ref(),
+            Exec::ColLocation,
+            FILL
+        );
+
+        let col_buy_price = text_input_template(
+            "Name of column where you write what you paid per item. Read by the program to calculate profit.",
+            (300.0, 100.0),
+            "Col buy price?",
+            "Ex: S",
+            sheet.col_buy_price.as_ref(),
+            Exec::ColBuyPrice,
+            FILL
+        );
+
+        let col_buy_date = text_input_template(
+            "Name of column where you write when you bought the item. Only read by the program.",
+            (300.0, 100.0),
+            "Col buy date?",
+            "Ex: T",
+            sheet.col_buy_date.as_ref(),
+            Exec::ColBuyDate,
+            FILL
+        );
+
+        let col_buy_currency = text_input_template(
+            "Name of column with the currency code (EUR, NOK, CNY...) the item was bought in. Only read by the program.\nThe buy price is converted with the exchange rate closest to the buy date. Empty cells are in the currency of the sheet.",
+            (300.0, 100.0),
+            "Col buy currency?",
+            "Ex: U",
+            sheet.col_buy_currency.as_ref(),
+            Exec::ColBuyCurrency,
+            FILL
+        );
+
+        let col_profit = if !user.fetch_prices { column![] }
+        else {
+            text_input_template(
+                "Name of column where the profit is written, (price - buy price) * quantity.",
+                (300.0, 100.0),
+                "Col profit?",
+                "Ex: U",
+                sheet.col_profit.as_ref(),
+                Exec::ColProfit,
+                FILL
+            )
+        };
+
+        let col_profit_pct = if !user.fetch_prices { column![] }
+        else {
+            text_input_template(
+                "Name of column where the profit in percent of the buy price is written.",
+                (300.0, 100.0),
+                "Col profit %?",
+                "Ex: V",
+                sheet.col_profit_pct.as_ref(),
+                Exec::ColProfitPct,
+                FILL
+            )
+        };
+
+        let col_manual = text_input_template(
+            "Name of column where the items from the manual items file are marked with their note (or 'Manual' if they have none).",
+            (300.0, 100.0),
+            "Col manual?",
+            "Ex: P",
+            sheet.col_manual.as_ref(),
+            Exec::ColManual,
+            FILL
+        );
+
         // Cells
         let cell_date = text_input_template(
             "Coordinates of cell where the current time and date can be written.",
@@ -974,6 +1459,43 @@ impl App {
             None::<Length>,
             Exec::BeginPathToSheet
         );
+        let storage_units_path = btn_base(
+            match &state.usersheet.user.storage_units_path {
+                Some(path) => format!(
+                    "Storage units: {}", {
+                        let tmp = path.to_str().unwrap_or("file").split("\\").collect::<Vec<_>>();
+                        tmp[tmp.len() - 1]
+                    }
+                ),
+                None => String::from("Storage units?"),
+            },
+            None::<Pixels>,
+            Some( FILL ),
+            None::<Length>,
+            Exec::BeginStorageUnitsPath
+        );
+        let manual_items_path = btn_base(
+            match &state.usersheet.user.manual_items_path {
+                Some(path) => format!(
+                    "Manual items: {}", {
+                        let tmp = path.to_str().unwrap_or("file").split("\\").collect::<Vec<_>>();
+                        tmp[tmp.len() - 1]
+                    }
+                ),
+                None => String::from("Manual items?"),
+            },
+            None::<Pixels>,
+            Some( FILL ),
+            None::<Length>,
+            Exec::BeginManualItemsPath
+        );
+        let restore_backup = btn_base(
+            "Restore backup",
+            None::<Pixels>,
+            Some( FILL ),
+            None::<Length>,
+            Exec::BeginRestoreBackup
+        );
         let run_program = btn_base(
             "Run",
             None::<Pixels>,
@@ -1006,13 +1528,13 @@ impl App {
 
         // Main pushes ------------------------------
         content = content.push( column![
-            row![path_to_sheet, load, save, run_program].padding(4).spacing(5),
+            row![path_to_sheet, storage_units_path, manual_items_path, load, save, restore_backup, run_program].padding(4).spacing(5),
             rule::horizontal(5),
         ]);
 
         if user.fetch_prices {
             content = content.push( column![
-                row![usd_to_x, pricing_provider, pricing_mode, iteminfo_provider].padding(4).spacing(5),
+                row![usd_to_x, pricing_provider, pricing_mode, iteminfo_provider, force_refresh].padding(4).spacing(5),
                 rule::horizontal(5),
                 ]
             )
@@ -1022,7 +1544,7 @@ impl App {
             row![ steamid, steamloginsecure, sheet_name, row_start_write, row_stop_write ].padding(4).spacing(5),
             rule::horizontal(5),
 
-            row![ pause_time_ms, ignore_steam_names, prefer_markets, percent_threshold ].padding(4).spacing(5),
+            row![ pause_time_ms, ignore_steam_names, prefer_markets, cache_ttl, market_fees, percent_threshold ].padding(4).spacing(5),
             rule::horizontal(5),
 
             row![col_full_name, col_gun_sticker_case, col_skin_name, col_wear, col_float ].padding(4).spacing(5),
@@ -1031,7 +1553,19 @@ impl App {
             row![col_pattern, col_phase, col_quantity, col_assetid, col_price, col_market].padding(4).spacing(5),
             rule::horizontal(5),
 
-            row![col_sold, col_inspect_link, col_csgoskins_link, cell_date, cell_usd_to_x].padding(4).spacing(5),
+            row![col_sold, col_inspect_link, col_csgoskins_link, cell_date, cell_usd_to_x, history_sheet_name].padding(4).spacing(5),
+            rule::horizontal(5),
+
+            row![col_previous_price, col_price_change, col_price_change_pct, col_net_price, col_location, col_manual, summary_sheet_name].padding(4).spacing(5),
+            rule::horizontal(5),
+
+            row![col_buy_price, col_buy_date, col_buy_currency, col_profit, col_profit_pct].padding(4).spacing(5),
+            rule::horizontal(5),
+
+            row![col_sold_date, col_sold_fee, realized_sheet_name].padding(4).spacing(5),
+            rule::horizontal(5),
+
+            row![dry_run, reconcile, col_status, quantity_decrease, col_quantity_removed, export_csv, export_jsonl].padding(4).spacing(50),
             rule::horizontal(5),
 
             row![ text_editor_template(ADDITIONAL_INFO, "-#- Program Output -#-", "", &state.editor_runtime_result, Length::Fill, Length::Fill, (1080.0, 260.0), Exec::RuntimeResult)],
@@ -9,9 +9,11 @@ use iced::widget::text_editor::{Action, Edit};
 use iced::widget::{checkbox, text_editor, Column, Row, TextInput};
 use iced::{Background, Pixels, Size, Task};
 use iced::{widget::{button, container, pick_list, slider, text::{IntoFragment, Wrapping}, text_input, tooltip, Button, Container, Tooltip, column, row}, Border, Color, Length, Renderer, Shadow, Theme};
+use indexmap::IndexMap;
 use num_traits::{AsPrimitive, FromPrimitive};
 
 use crate::gui::ice::{Exec};
+use crate::models::web::Sites;
 use crate::parsing::sanitizing::IsEnglishAlphabetic;
 
 const BG_MAIN: Color = Color::from_rgba8(181, 100, 255, 1.0);
@@ -483,6 +485,16 @@ impl AssignFromStr for Option<String> {
     }
 }
 
+/// Reads `market=value, market=value` from the text editors, entries that can't be read are skipped
+pub fn parse_market_values<T>(text: &str, value: impl Fn(&str) -> Option<T>) -> IndexMap<Sites, T> {
+    text.split(",")
+        .filter_map(|s| {
+            let (market, v) = s.split_once("=")?;
+            Some( (Sites::from_str(market.trim()).ok()?, value(v.trim())?) )
+        })
+        .collect::<IndexMap<Sites, T>>()
+}
+
 pub fn task_col_if_english_alphabetic<S: AssignFromStr>(state_val: &mut S, s: &str) -> Task<Exec> {
     if s.chars().any(|c| !c.is_english_alphabetic() ) { return Task::none() }
     state_val.assign_from(s);
@@ -494,3 +506,23 @@ pub fn task_cell_if_english_alphabetic(state_val: &mut Option<String>, s: &str)
     *state_val = s.to_option();
     Task::none()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn market_values_are_read_from_the_editor_text() {
+        let ttl = parse_market_values("STEAM=60, csfloat = 15,nope=5, BUFF163=x, SKINPORT", |m| m.parse::<u32>().ok());
+
+        assert_eq!(ttl.into_iter().collect::<Vec<(Sites, u32)>>(), vec![(Sites::STEAM, 60), (Sites::CSFLOAT, 15)]);
+        assert!(parse_market_values("", |m| m.parse::<u32>().ok()).is_empty());
+    }
+
+    #[test]
+    fn fees_can_have_a_percent_sign() {
+        let fees = parse_market_values("STEAM=13, YOUPIN = 1.5 %, CSFLOAT=free", |fee| fee.trim_end_matches('%').trim().parse::<f64>().ok());
+
+        assert_eq!(fees.into_iter().collect::<Vec<(Sites, f64)>>(), vec![(Sites::STEAM, 13.0), (Sites::YOUPIN, 1.5)]);
+    }
+}
@@ -12,7 +12,9 @@ pub mod excel;
 pub mod models;
 pub mod parsing;
 pub mod browser;
+pub mod storage;
 
+// Default for the markets that don't have a TTL set in UserInfo.cache_ttl_minutes
 const CACHE_TIME: std::time::Duration = std::time::Duration::from_secs(60 * 60 * 6);
 
 #[derive(Debug)]
@@ -1,13 +1,46 @@
+use chrono::NaiveDate;
+
 /// Used to keep track of the data fetched from the Excel spreadsheet. Only contains information that is neccessary to have when update / inserting data in the spreadsheet
 #[derive(Debug)]
 pub struct ExcelData {
     pub name: String, 
-    // pub price: f64,
     pub quantity: Option<u16>,        // Hvis items ikke er group'a together, så har de None quantity
     // pub inspect_link: Option<String>, // Brukes for å inspecte + pricechecke hvis special er noe, aka hvis det er en sapphire så kan prisen 
     pub phase: Option<String>,        // for en sapphire hentes korrekt via float api'et til csgotrader // csfloat
     pub asset_id: Option<u64>,        // Unik ID brukes hvis man ikke grupperer samme items
-    pub sold: Option<f64>
+    pub sold: Option<f64>,
+    pub previous_price: Option<f64>,  // Prisen som stod i col_price før runnen
+    pub price: Option<f64>,           // Prisen som ble skrevet til spreadsheetn denne runnen
+    pub market: Option<String>,
+    pub net_price: Option<f64>,       // Prisen etter fee, fra de samme marketsa som prisen
+    pub float: Option<f64>,
+    pub pattern: Option<u32>,
+    pub buy_price: Option<f64>,       // Hva man betalte per item, skrevet inn av brukeren
+    pub buy_date: Option<String>,
+    pub buy_currency: Option<String>, // Valutakoden kjøpsprisen er i, None er samme som spreadsheetn
+    pub sold_date: Option<String>,
+    pub sold_fee: Option<f64>         // Fee betalt per item når det ble solgt
+}
+
+/// One sold row in the realized gains sheet, prices are per item in the currency of the sheet
+#[derive(Debug)]
+pub struct RealizedRow {
+    pub steamid: u64,
+    pub sheet: String,
+    pub name: String,
+    pub quantity: u16,
+    pub sold_date: Option<NaiveDate>,
+    pub sale_price: f64,
+    pub buy_price: Option<f64>,
+    pub fee: f64,
+    pub holding_days: Option<i64>
+}
+
+impl RealizedRow {
+    /// (sale price - fee - buy price) * quantity, None without a buy price
+    pub fn profit(&self) -> Option<f64> {
+        self.buy_price.map(|buy_price| (self.sale_price - self.fee - buy_price) * self.quantity as f64)
+    }
 }                                   
 // 
 // #[derive(Debug)]
@@ -9,7 +9,10 @@ pub enum PricingMode {
     Cheapest,
     MostExpensive,
     Hierarchical,
-    Random
+    Random,
+    BestNet,
+    Median,
+    Average
 }
 impl PricingMode {
     pub fn as_str(&self) -> &'static str {
@@ -18,6 +21,9 @@ impl PricingMode {
             PricingMode::MostExpensive => "Most Expensive",
             PricingMode::Hierarchical => "Hierarchical",
             PricingMode::Random => "Random",
+            PricingMode::BestNet => "Best Net",
+            PricingMode::Median => "Median",
+            PricingMode::Average => "Average",
         }
     }
 }
@@ -35,6 +41,9 @@ impl FromStr for PricingMode {
             "most" => Ok(Self::MostExpensive),
             "hier" => Ok(Self::Hierarchical),
             "r" => Ok(Self::Random),
+            "bestnet" | "best net" | "net" => Ok(Self::BestNet),
+            "median" | "med" => Ok(Self::Median),
+            "average" | "avg" | "mean" => Ok(Self::Average),
             _ => Err( format!("Pricingmode of {} not allowed.", s))
         }
     }
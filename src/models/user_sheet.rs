@@ -1,7 +1,8 @@
-use std::path::PathBuf;
+use std::{fmt, path::PathBuf, str::FromStr};
 
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use serde::{Deserialize, Serialize};
+use strum::EnumIter;
 
 use super::{price::{Currencies, PricingMode, PricingProvider}, web::{ItemInfoProvider, Sites}};
 
@@ -21,7 +22,108 @@ pub struct UserInfo {
     pub ignore_already_sold: bool,
     pub group_simular_items: bool,
     pub fetch_prices: bool, 
-    pub fetch_steam: bool
+    pub fetch_steam: bool,
+    #[serde(default)]
+    pub offline: bool, // Use the cached inventory, prices and exchange rates instead of fetching them
+    #[serde(default)]
+    pub cache_ttl_minutes: IndexMap<Sites, u32>, // How long the cached prices of a market are used before fetching them again
+    #[serde(default)]
+    pub market_fees: IndexMap<Sites, f64>, // Percent of the sale price a market keeps, overrides Sites::default_fee_pct
+    #[serde(default)]
+    pub force_refresh: bool, // Fetch the prices even if the cache is still valid
+    #[serde(default)]
+    pub storage_units_path: Option<PathBuf>, // JSON export of the storage unit contents, added to the inventory items
+    #[serde(default)]
+    pub manual_items_path: Option<PathBuf>, // JSON list of items that aren't in the inventory, priced like the rest
+    #[serde(default)]
+    pub quantity_decrease: QuantityDecrease, // What to do when grouping and the inventory has less of an item than the sheet
+    #[serde(default)]
+    pub reconcile: bool, // Mark the rows that aren't in the inventory anymore
+    #[serde(default = "default_backup_count")]
+    pub backup_count: u8, // How many backups of the spreadsheet to keep, 0 turns backups off
+    #[serde(default)]
+    pub dry_run: bool, // Show what would change in the spreadsheet without writing anything
+    #[serde(default)]
+    pub export_csv: bool, // Write the rows to a csv file next to the spreadsheet after every run
+    #[serde(default)]
+    pub export_jsonl: bool, // Same as export_csv, but JSON Lines
+    #[serde(default)]
+    pub accounts: Vec<Account> // IF NOT EMPTY, runs every account instead of steamid/steamloginsecure
+}
+
+/// What happens to the quantity in the sheet when the inventory has less of the item (sold, traded away etc.)
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, EnumIter, PartialEq, Eq, Hash)]
+pub enum QuantityDecrease {
+    Never, // Only raise quantities, like it always has
+    Always, // Lower the quantity to what's in the inventory
+    Record // Lower it and add how many were removed to col_quantity_removed
+}
+impl QuantityDecrease {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuantityDecrease::Never => "Never",
+            QuantityDecrease::Always => "Always",
+            QuantityDecrease::Record => "Record",
+        }
+    }
+}
+
+impl FromStr for QuantityDecrease {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "never" | "n" | "" => Ok(Self::Never),
+            "always" | "a" => Ok(Self::Always),
+            "record" | "r" => Ok(Self::Record),
+            _ => Err( format!("Quantity decrease of {} not allowed. Use never, always or record.", s) )
+        }
+    }
+}
+
+impl fmt::Display for QuantityDecrease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Default for QuantityDecrease {
+    fn default() -> Self {
+        Self::Never
+    }
+}
+
+/// An inventory to run in the same run as the others, written to its own sheet in the same spreadsheet
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Account {
+    pub steamid: u64,
+    pub steamloginsecure: Option<String>,
+    pub sheet_name: Option<String>, // Defaults to the steamid
+    #[serde(default)]
+    pub storage_units_path: Option<PathBuf>,
+    #[serde(default)]
+    pub manual_items_path: Option<PathBuf>
+}
+
+fn default_backup_count() -> u8 { crate::storage::backup::DEFAULT_BACKUP_COUNT }
+
+impl UserInfo {
+    /// How long the cached prices of `market` are valid. Falls back to `CACHE_TIME` for markets without a TTL set.
+    pub fn cache_ttl(&self, market: Sites) -> std::time::Duration {
+        self.cache_ttl_minutes.get(&market)
+            .map(|m| std::time::Duration::from_secs(*m as u64 * 60))
+            .unwrap_or(crate::CACHE_TIME)
+    }
+
+    /// Fee of `market` in percent. Falls back to `Sites::default_fee_pct` for markets without a fee set.
+    pub fn fee_pct(&self, market: Sites) -> f64 {
+        self.market_fees.get(&market).copied().unwrap_or(market.default_fee_pct())
+    }
+
+    /// What's left of `price` after selling it on `market`
+    pub fn net_price(&self, market: Sites, price: f64) -> f64 {
+        price * (1.0 - self.fee_pct(market) / 100.0)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
@@ -36,16 +138,34 @@ pub struct SheetInfo {
     pub col_skin_name: Option<String>, // Where to put the name of skin/player/team
     pub col_wear: Option<String>, // Where to put float of skin/rarity of sticker 
     pub sheet_name: Option<String>, // Name of the sheet user wants to access
+    pub history_sheet_name: Option<String>, // IF PROVIDED, a row with the total value is added to this sheet every run
+    pub summary_sheet_name: Option<String>, // IF PROVIDED, the total value of every account is written to this sheet
+    pub realized_sheet_name: Option<String>, // IF PROVIDED, the profit of every sold row and totals per month are written to this sheet
     pub col_sold: Option<String>, // IF PROVIDED, ignore updating price of stuff that is already sold
+    pub col_sold_date: Option<String>, // IF PROVIDED, when it was sold, filled in by the user
+    pub col_sold_fee: Option<String>, // IF PROVIDED, fee paid per item when it was sold, filled in by the user
     pub col_steam_name: String, // Column where the full market name to the site used to pricecheck is
     pub col_asset_id: Option<String>, // UNIQUE IDENTIFIER!
     pub col_price: String, // Column for the price of item
+    pub col_previous_price: Option<String>, // Column for the price of item before this run
+    pub col_price_change: Option<String>, // Column for how much the price changed since last run
+    pub col_price_change_pct: Option<String>, // Column for how much the price changed since last run in percent
+    pub col_net_price: Option<String>, // Column for the price after the fee of the market(s) it's from
     pub col_quantity: Option<String>, // Column for the item quantity
+    pub col_buy_price: Option<String>, // IF PROVIDED, what was paid per item, filled in by the user
+    pub col_buy_date: Option<String>, // IF PROVIDED, when it was bought, filled in by the user
+    pub col_buy_currency: Option<String>, // IF PROVIDED, currency code the buy price is in, empty is the currency of the sheet
+    pub col_profit: Option<String>, // Column for (price - buy price) * quantity
+    pub col_profit_pct: Option<String>, // Column for the profit in percent of the buy price
+    pub col_quantity_removed: Option<String>, // IF PROVIDED, how many of the item have left the inventory in total (QuantityDecrease::Record)
     pub col_inspect_link: Option<String>,
     pub col_csgoskins_link: Option<String>,
     pub col_phase: Option<String>, // IF YOU WANT THE CORRECT DOPPLER PRICES, SET THIS ROW
     pub col_pattern: Option<String>,
     pub col_float: Option<String>,
+    pub col_location: Option<String>, // IF PROVIDED, where the item is (Inventory or the storage unit name)
+    pub col_manual: Option<String>, // IF PROVIDED, marks the items from the manual items file with their note
+    pub col_status: Option<String>, // IF PROVIDED, reconciling writes if the item is still in the inventory here instead of striking it through
 
 }
 
@@ -53,4 +173,39 @@ pub struct SheetInfo {
 pub struct UserSheet {
     pub user: UserInfo,
     pub sheet: SheetInfo
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fees_default_per_market_unless_set() {
+        let mut user = UserInfo::default();
+        assert_eq!(user.fee_pct(Sites::STEAM), 13.0);
+        assert_eq!(user.fee_pct(Sites::BUFF163), 2.5);
+
+        user.market_fees.insert(Sites::STEAM, 15.0);
+        user.market_fees.insert(Sites::CSFLOAT, 0.0);
+        assert_eq!(user.fee_pct(Sites::STEAM), 15.0);
+        assert_eq!(user.fee_pct(Sites::CSFLOAT), 0.0);
+        assert_eq!(user.fee_pct(Sites::SKINPORT), 8.0);
+    }
+
+    #[test]
+    fn net_price_is_after_the_fee() {
+        let mut user = UserInfo::default();
+        user.market_fees.insert(Sites::SKINPORT, 100.0);
+
+        assert_eq!(user.net_price(Sites::STEAM, 100.0), 87.0);
+        assert_eq!(user.net_price(Sites::YOUPIN, 50.0), 49.5);
+        assert_eq!(user.net_price(Sites::SKINPORT, 50.0), 0.0);
+        assert_eq!(user.net_price(Sites::CSFLOAT, 0.0), 0.0);
+    }
+
+    #[test]
+    fn best_net_is_parsed() {
+        for s in ["bestnet", "Best Net", "net"] {
+            assert!(matches!(PricingMode::from_str(s), Ok(PricingMode::BestNet)), "{}", s);
+        }
+    }
+}
@@ -69,6 +69,18 @@ impl Sites {
             Sites::STEAM => {false}
         }
     }
+    /// Roughly how many percent of the sale price the market keeps when you sell, used if it's not set in UserInfo.market_fees
+    pub fn default_fee_pct(&self) -> f64 {
+        match *self {
+            Sites::CSFLOAT => 2.0,
+            Sites::BUFF163 => 2.5,
+            Sites::YOUPIN => 1.0,
+            Sites::CSMONEY => 5.0,
+            Sites::BITSKINS => 5.0,
+            Sites::SKINPORT => 8.0,
+            Sites::STEAM => 13.0
+        }
+    }
 }
 
 // ------------------------------------------------------------
@@ -81,13 +93,16 @@ pub struct SteamData {
     pub float: Option<f64>,
     pub name: String,
     pub inspect_link: Option<String>,
+    pub location: Option<String>, // Storage unit(s) the item is in, None if only in the inventory
+    pub phase: Option<Doppler>, // Known without fetching iteminfo, only set for manual items
+    pub manual_note: Option<String>, // Some if the item is from the manual items file
      // IF MODE IS !GROUP_SIMULAR_ITEMS, THIS IS UNIQUE IDENTIFIER
     // pub instance_id: u64, // Used POTENTIALLY for batched float getting
 }
 
 // ------------------------------------------------------------
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct SteamJson {
     pub assets: Vec<Value>,
     pub asset_properties: Option<Vec<Value>>,
@@ -95,6 +110,8 @@ pub struct SteamJson {
     pub total_inventory_count: u16,
     pub success: i8,
     pub rwgrsn: i8,
+    pub more_items: Option<u8>, // Only there if the inventory has more pages
+    pub last_assetid: Option<String>, // Cursor for the next page
 }
 
 // ------------------------------------------------------------
@@ -105,6 +122,12 @@ pub struct CachedMarket {
     pub timestamp: DateTime<Utc>
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct CachedInventory {
+    pub inventory: SteamJson,
+    pub timestamp: DateTime<Utc>
+}
+
 // ------------------------------------------------------------
 
 pub struct ExtraItemData {
@@ -0,0 +1,121 @@
+use std::sync::LazyLock;
+
+use ahash::{HashMap, HashMapExt};
+use regex::Regex;
+use serde_json::Value;
+
+use crate::models::web::Sites;
+
+static JSON_LD: LazyLock<Regex> = LazyLock::new(||
+    Regex::new(r#"(?s)<script[^>]*type="application/ld\+json"[^>]*>(.*?)</script>"#).unwrap()
+);
+
+// Fallback for pages without structured data: market link followed by the first dollar price in the same row
+static MARKET_LINK: LazyLock<Regex> = LazyLock::new(||
+    Regex::new(r#"href="[^"]*/markets/([a-z0-9\-]+)/?"[^>]*>"#).unwrap()
+);
+static ROW_END: LazyLock<Regex> = LazyLock::new(||
+    Regex::new(r#"</(?:div|li|tr)>"#).unwrap()
+);
+static DOLLAR_PRICE: LazyLock<Regex> = LazyLock::new(||
+    Regex::new(r#"\$\s*([0-9][0-9,]*(?:\.[0-9]+)?)"#).unwrap()
+);
+
+/// Parses the offers on a csgoskins.gg item page (HTML, or the JSON-LD data of it) into the cheapest USD price per market.
+pub fn parse_offers(body: &str) -> HashMap<Sites, f64> {
+    let mut offers: HashMap<Sites, f64> = HashMap::new();
+    let trimmed = body.trim_start();
+
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        if let Ok(json) = serde_json::from_str::<Value>(trimmed) { offers_from_json(&json, &mut offers); }
+        return offers;
+    }
+
+    for script in JSON_LD.captures_iter(body) {
+        if let Ok(json) = serde_json::from_str::<Value>(&script[1]) { offers_from_json(&json, &mut offers); }
+    }
+
+    if offers.is_empty() {
+        let links = MARKET_LINK.captures_iter(body).collect::<Vec<regex::Captures>>();
+
+        for (i, link) in links.iter().enumerate() {
+            // A market without a price can't take the price of the next one
+            let start = link.get(0).map(|m| m.end()).unwrap_or_default();
+            let end = links.get(i + 1).and_then(|next| next.get(0)).map(|m| m.start()).unwrap_or(body.len());
+            let row = &body[start..end];
+            let row = ROW_END.find(row).map(|m| &row[..m.start()]).unwrap_or(row);
+
+            if let Some(site) = site_from_market_name(&link[1])
+            && let Some(price) = DOLLAR_PRICE.captures(row)
+            && let Ok(price) = price[1].replace(",", "").parse::<f64>() {
+                insert_cheapest(&mut offers, site, price);
+            }
+        }
+    }
+    offers
+}
+
+fn offers_from_json(json: &Value, offers: &mut HashMap<Sites, f64>) {
+    match json {
+        Value::Array(arr) => { for v in arr { offers_from_json(v, offers) } },
+        Value::Object(obj) => {
+            if let Some(site) = offer_site(json)
+            && let Some(price) = offer_price(json)
+            && json.get("priceCurrency").and_then(|c| c.as_str()).is_none_or(|c| c.eq_ignore_ascii_case("usd"))
+            {
+                insert_cheapest(offers, site, price);
+            }
+            // AggregateOffer and @graph nest the actual offers
+            for key in ["offers", "@graph"] {
+                if let Some(inner) = obj.get(key) { offers_from_json(inner, offers) }
+            }
+        },
+        _ => {}
+    }
+}
+
+fn offer_site(offer: &Value) -> Option<Sites> {
+    let seller = offer.get("seller").and_then(|s| {
+        s.as_str().or_else(|| s.get("name").and_then(|n| n.as_str()))
+    });
+
+    let from_url = offer.get("url")
+        .and_then(|u| u.as_str())
+        .and_then(|u| u.split("/markets/").nth(1))
+        .and_then(|u| u.split(['/', '?']).next());
+
+    seller.and_then(site_from_market_name)
+        .or_else(|| from_url.and_then(site_from_market_name))
+}
+
+fn offer_price(offer: &Value) -> Option<f64> {
+    let price = offer.get("price").or_else(|| offer.get("lowPrice"))?;
+
+    if let Some(p) = price.as_f64() { Some(p) }
+    else { price.as_str().and_then(|p| p.replace(",", "").trim().parse::<f64>().ok()) }
+}
+
+fn insert_cheapest(offers: &mut HashMap<Sites, f64>, site: Sites, price: f64) {
+    offers.entry(site)
+        .and_modify(|p| if price < *p { *p = price })
+        .or_insert(price);
+}
+
+/// Maps the market names/slugs used by csgoskins.gg to `Sites`.
+pub fn site_from_market_name(market: &str) -> Option<Sites> {
+    let clean = market.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+
+    match clean.as_str() {
+        "csfloat" | "csgofloat" | "csfloatmarket" => Some(Sites::CSFLOAT),
+        "buff163" | "buff" => Some(Sites::BUFF163),
+        "youpin" | "youpin898" | "uuyoupin" => Some(Sites::YOUPIN),
+        "csmoney" | "csmoneymarket" => Some(Sites::CSMONEY),
+        "bitskins" => Some(Sites::BITSKINS),
+        "skinport" => Some(Sites::SKINPORT),
+        "steam" | "steamcommunity" | "steamcommunitymarket" | "steammarket" => Some(Sites::STEAM),
+        _ => None
+    }
+}
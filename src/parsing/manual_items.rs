@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::models::{price::Doppler, web::SteamData};
+
+/// An item written into the manual items file, for items that aren't in the steam inventory
+/// (on tradehold on another account, lent to a friend, listed on a 3rd party site etc.)
+#[derive(Debug, Deserialize)]
+pub struct ManualItem {
+    #[serde(alias = "market_hash_name", alias = "market_name")]
+    pub name: String,
+    #[serde(default = "default_quantity")]
+    pub quantity: u16,
+    #[serde(default)]
+    pub phase: Option<String>,
+    #[serde(default)]
+    pub float: Option<f64>,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default, alias = "assetid", alias = "id")]
+    pub asset_id: Option<u64>, // Only needed when not grouping simular items, any number that's unique works
+}
+
+fn default_quantity() -> u16 { 1 }
+
+/// Reads the manual items file, which is a JSON array of `ManualItem`.
+pub fn load_manual_items(path: &Path) -> Result<Vec<SteamData>, String> {
+    let file = std::fs::read(path).map_err(|e| format!("Couldn't read manual items file {}. \n{}", path.display(), e))?;
+    let items = serde_json::from_slice::<Vec<ManualItem>>(&file)
+        .map_err(|e| format!("Manual items file {} is not valid. \n{}", path.display(), e))?;
+
+    items.into_iter()
+        .filter(|item| !item.name.trim().is_empty())
+        .map(|item| {
+            let phase = match &item.phase {
+                Some(p) if !p.trim().is_empty() => Some( p.parse::<Doppler>().map_err(|e| format!("{} in manual item {}", e, item.name))? ),
+                _ => None
+            };
+
+            Ok( SteamData {
+                name: item.name.trim().to_string(),
+                quantity: Some(item.quantity),
+                asset_id: item.asset_id.unwrap_or(0),
+                float: item.float,
+                pattern: None,
+                inspect_link: None,
+                location: None,
+                phase,
+                manual_note: Some( item.note.unwrap_or_default() )
+            } )
+        })
+        .collect()
+}
+
+/// Adds the manual items to the items from the inventory.
+///
+/// When grouping, a manual item without a phase that has the same name as an inventory item is added to its quantity,
+/// and the row keeps the manual note so it can be told apart. Otherwise manual items need an assetid to be told apart,
+/// so the ones without are skipped. Returns how many were skipped.
+pub fn merge_manual_items(inventory: &mut Vec<SteamData>, manual: Vec<SteamData>, group_simular_items: bool) -> usize {
+    let mut skipped: usize = 0;
+
+    for item in manual {
+        if !group_simular_items {
+            if item.asset_id == 0 { skipped += 1; continue }
+            inventory.push(item);
+            continue;
+        }
+
+        let existing = if item.phase.is_none() {
+            inventory.iter_mut().find(|s| s.name == item.name && s.phase.is_none())
+        } else { None };
+
+        match existing {
+            Some(existing) => {
+                existing.quantity = Some( existing.quantity.unwrap_or(1).saturating_add(item.quantity.unwrap_or(1)) );
+
+                // Inventory row that now has manual items in it too, manual items on their own have no location
+                if existing.manual_note.is_none() {
+                    existing.location = Some( format!("{}, Manual", existing.location.as_deref().unwrap_or("Inventory")) );
+                }
+                existing.manual_note = Some( join_notes(existing.manual_note.take(), item.manual_note) );
+            },
+            None => inventory.push(item)
+        }
+    }
+    skipped
+}
+
+fn join_notes(a: Option<String>, b: Option<String>) -> String {
+    [a, b].into_iter()
+        .flatten()
+        .filter(|n| !n.is_empty())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, quantity: Option<u16>, asset_id: u64, manual_note: Option<&str>) -> SteamData {
+        SteamData {
+            name: name.to_string(),
+            quantity,
+            asset_id,
+            float: None,
+            pattern: None,
+            inspect_link: None,
+            location: None,
+            phase: None,
+            manual_note: manual_note.map(|n| n.to_string())
+        }
+    }
+
+    #[test]
+    fn ungrouped_keeps_quantity_and_skips_without_assetid() {
+        let mut inventory = vec![item("AK-47 | Redline (Field-Tested)", None, 11, None)];
+        let manual = vec![
+            item("AK-47 | Redline (Field-Tested)", Some(3), 12, Some("Listed on csfloat")),
+            item("AWP | Asiimov (Field-Tested)", Some(1), 0, Some(""))
+        ];
+
+        assert_eq!(merge_manual_items(&mut inventory, manual, false), 1);
+        assert_eq!(inventory.len(), 2);
+        assert_eq!(inventory[1].quantity, Some(3));
+        assert_eq!(inventory[1].manual_note.as_deref(), Some("Listed on csfloat"));
+    }
+
+    #[test]
+    fn grouped_adds_to_the_inventory_row_and_keeps_it_marked() {
+        let mut inventory = vec![item("AK-47 | Redline (Field-Tested)", Some(2), 11, None)];
+        let manual = vec![
+            item("AK-47 | Redline (Field-Tested)", Some(3), 0, Some("Lent to a friend")),
+            item("AK-47 | Redline (Field-Tested)", Some(1), 0, Some(""))
+        ];
+
+        assert_eq!(merge_manual_items(&mut inventory, manual, true), 0);
+        assert_eq!(inventory.len(), 1);
+        assert_eq!(inventory[0].quantity, Some(6));
+        assert_eq!(inventory[0].location.as_deref(), Some("Inventory, Manual"));
+        assert_eq!(inventory[0].manual_note.as_deref(), Some("Lent to a friend"));
+    }
+
+    #[test]
+    fn grouped_manual_items_of_the_same_name_stay_without_location() {
+        let mut inventory = Vec::new();
+        let manual = vec![
+            item("Sticker | Crown (Foil)", Some(1), 0, Some("Binder")),
+            item("Sticker | Crown (Foil)", Some(2), 0, Some("Old account"))
+        ];
+
+        merge_manual_items(&mut inventory, manual, true);
+        assert_eq!(inventory.len(), 1);
+        assert_eq!(inventory[0].quantity, Some(3));
+        assert_eq!(inventory[0].location, None);
+        assert_eq!(inventory[0].manual_note.as_deref(), Some("Binder, Old account"));
+    }
+}
@@ -1,6 +1,9 @@
 pub mod item_csgotrader;
+pub mod item_csgoskins;
 pub mod item_csfloat;
 pub mod csgoskins_url;
 pub mod market_name_parse;
 pub mod sanitizing;
 pub mod load_file;
+pub mod storage_units;
+pub mod manual_items;
@@ -1,6 +1,6 @@
 use crate::{
     models::{
-        user_sheet::{UserInfo, SheetInfo},
+        user_sheet::{QuantityDecrease, UserInfo, SheetInfo},
         price::{Currencies, PricingMode},
         web::{ItemInfoProvider, Sites}
     },
@@ -35,6 +35,7 @@ pub fn sanitize_and_check_user_input(
         excel.col_asset_id = None;
     } else {
         excel.col_quantity = None;
+        excel.col_quantity_removed = None;
     }
 
     if user.usd_to_x != Currencies::None && excel.rowcol_usd_to_x.is_some() {
@@ -82,6 +83,14 @@ pub fn sanitize_and_check_user_input(
         err_str.push_str("Sheet name can't be None when fetching prices without fetching Steam.\n");
     }
 
+    if let Some(hsn) = &excel.history_sheet_name && excel.sheet_name.as_ref().is_some_and(|sn| sn == hsn) {
+        err_str.push_str("History sheet can't be the same sheet as the one the items are written to.\n");
+    }
+
+    if excel.history_sheet_name.is_some() && !user.fetch_prices {
+        warn_str.push_str("WARNING: History sheet is only updated when fetching prices.\n");
+    }
+
     if user.pause_time_ms < 1000 || user.pause_time_ms > 2500 {
         err_str.push_str("Pause Time is only allowed to be in range of 1000 (1 second) - 2500 (2.5 seconds).\n");
     }
@@ -110,6 +119,10 @@ pub fn sanitize_and_check_user_input(
         err_str.push_str("Pricing mode can't be Hierarchical if the Percent threshold is None.\n");
     }
 
+    if let Some((market, fee)) = user.market_fees.iter().find(|(_, fee)| !(0.0..=100.0).contains(*fee)) {
+        err_str.push_str(&format!("Fee of {} has to be between 0 and 100 percent, not {}.\n", market, fee));
+    }
+
     if excel.col_steam_name.is_empty() {
         err_str.push_str("Column for full names of the item(s) can't be empty.\n");
     }
@@ -120,12 +133,79 @@ pub fn sanitize_and_check_user_input(
 
 
     if user.fetch_steam {
-        match user.steamid.checked_ilog10() {
-            Some(check) => { if check > 17 { err_str.push_str("SteamID is invalid.\n"); } }
-            None => { err_str.push_str("SteamID is invalid.\n"); }
+        let steamids: Vec<u64> = if user.accounts.is_empty() { Vec::from([user.steamid]) }
+        else { user.accounts.iter().map(|a| a.steamid).collect() };
+
+        for steamid in steamids {
+            match steamid.checked_ilog10() {
+                Some(check) => { if check > 17 { err_str.push_str(&format!("SteamID {} is invalid.\n", steamid)); } }
+                None => { err_str.push_str(&format!("SteamID {} is invalid.\n", steamid)); }
+            }
         }
     }
 
+    // Sheets the items are written to, an account without a sheet name uses the first sheet when it's the only one
+    let mut account_sheets: Vec<String> = if user.accounts.is_empty() { excel.sheet_name.iter().cloned().collect() }
+    else if user.accounts.len() == 1 { user.accounts[0].sheet_name.iter().cloned().collect() }
+    else {
+        user.accounts.iter()
+            .map(|a| a.sheet_name.clone().unwrap_or_else(|| a.steamid.to_string()))
+            .collect()
+    };
+    account_sheets.sort();
+
+    if let Some(w) = account_sheets.windows(2).find(|w| w[0] == w[1]) {
+        err_str.push_str(&format!("Two or more accounts are written to the same sheet: '{}'\n", w[0]));
+    }
+
+    // They're rewritten or appended to every run, which would write over the items
+    for special in [&excel.summary_sheet_name, &excel.history_sheet_name, &excel.realized_sheet_name].into_iter().flatten() {
+        if account_sheets.contains(special) {
+            err_str.push_str(&format!("The sheet '{}' can't be both a sheet the items are written to and the summary/history/realized gains sheet.\n", special));
+        }
+    }
+
+    // Sold values are only read when ignoring already sold items
+    if excel.realized_sheet_name.is_some() && !user.ignore_already_sold {
+        err_str.push_str("Ignore already sold has to be on to write the realized gains sheet.\n");
+    }
+
+    let storage_units_paths = user.accounts.iter()
+        .filter_map(|a| a.storage_units_path.as_ref())
+        .chain( user.storage_units_path.iter().filter(|_| user.accounts.is_empty()) );
+
+    for path in storage_units_paths {
+        if !path.is_file() {
+            err_str.push_str(&format!("Storage unit file {} doesn't exist.\n", path.display()));
+        }
+    }
+
+    let manual_items_paths = user.accounts.iter()
+        .filter_map(|a| a.manual_items_path.as_ref())
+        .chain( user.manual_items_path.iter().filter(|_| user.accounts.is_empty()) );
+
+    for path in manual_items_paths {
+        if !path.is_file() {
+            err_str.push_str(&format!("Manual items file {} doesn't exist.\n", path.display()));
+        }
+    }
+
+    if user.quantity_decrease == QuantityDecrease::Record && excel.col_quantity_removed.is_none() {
+        warn_str.push_str("WARNING: Quantity decrease is Record but the quantity removed column isn't set, so the quantities are only lowered.\n");
+    }
+
+    if (excel.col_profit.is_some() || excel.col_profit_pct.is_some()) && excel.col_buy_price.is_none() {
+        err_str.push_str("Buy price column has to be given to calculate profit.\n");
+    }
+
+    if excel.col_buy_currency.is_some() && excel.col_buy_price.is_none() {
+        err_str.push_str("Buy price column has to be given when the buy currency column is.\n");
+    }
+
+    if user.reconcile && !user.fetch_steam {
+        warn_str.push_str("WARNING: Missing items are only marked when fetching from Steam, nothing is marked from storage units or manual items alone.\n");
+    }
+
     if excel.row_start_write_in_table == 0 {
         err_str.push_str("Row to start writing in the spreadsheet is invalid.\n");
     }
@@ -173,12 +253,27 @@ pub fn sanitize_and_check_user_input(
     if let Some(x) = &excel.col_float { all_excel.push(x) }
     if let Some(x) = &excel.col_gun_sticker_case { all_excel.push(x) }
     if let Some(x) = &excel.col_inspect_link { all_excel.push(x) }
+    if let Some(x) = &excel.col_location { all_excel.push(x) }
+    if let Some(x) = &excel.col_manual { all_excel.push(x) }
     if let Some(x) = &excel.col_market { all_excel.push(x) }
     if let Some(x) = &excel.col_pattern { all_excel.push(x) }
+    if let Some(x) = &excel.col_previous_price { all_excel.push(x) }
+    if let Some(x) = &excel.col_price_change { all_excel.push(x) }
+    if let Some(x) = &excel.col_price_change_pct { all_excel.push(x) }
+    if let Some(x) = &excel.col_net_price { all_excel.push(x) }
     if let Some(x) = &excel.col_phase { all_excel.push(x) }
     if let Some(x) = &excel.col_quantity { all_excel.push(x) }
+    if let Some(x) = &excel.col_quantity_removed { all_excel.push(x) }
+    if let Some(x) = &excel.col_buy_price { all_excel.push(x) }
+    if let Some(x) = &excel.col_buy_date { all_excel.push(x) }
+    if let Some(x) = &excel.col_buy_currency { all_excel.push(x) }
+    if let Some(x) = &excel.col_sold_date { all_excel.push(x) }
+    if let Some(x) = &excel.col_sold_fee { all_excel.push(x) }
+    if let Some(x) = &excel.col_profit { all_excel.push(x) }
+    if let Some(x) = &excel.col_profit_pct { all_excel.push(x) }
     if let Some(x) = &excel.col_skin_name { all_excel.push(x) }
     if let Some(x) = &excel.col_sold { all_excel.push(x) }
+    if let Some(x) = &excel.col_status { all_excel.push(x) }
     if let Some(x) = &excel.col_wear { all_excel.push(x) }
     all_excel.sort();
 
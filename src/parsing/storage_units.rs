@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use ahash::{HashMap, HashMapExt};
+use serde_json::Value;
+
+use crate::models::web::SteamData;
+
+const DEFAULT_LOCATION: &str = "Storage Unit";
+
+/// Reads the items inside storage units from a JSON file exported by a casket dump tool.
+///
+/// Handles the usual shapes those tools export:
+/// - `[{ "name": "Casket", "items": [item, ...] }, ...]`
+/// - `{ "Casket": [item, ...], ... }`
+/// - `[item, ...]` where every item has its own `casket_name`/`storage_unit`/`location`
+///
+/// An item needs a `market_hash_name`/`market_name`/`name`, and can have `quantity`, `assetid`, `paintwear`/`float` and `paintseed`/`pattern`.
+pub fn load_storage_units(path: &Path) -> Result<Vec<SteamData>, String> {
+    let file = std::fs::read(path).map_err(|e| format!("Couldn't read storage unit file {}. \n{}", path.display(), e))?;
+    let json = serde_json::from_slice::<Value>(&file)
+        .map_err(|e| format!("Storage unit file {} is not valid JSON. \n{}", path.display(), e))?;
+
+    let mut items: Vec<SteamData> = Vec::new();
+
+    match &json {
+        Value::Array(arr) => {
+            for entry in arr {
+                if let Some(casket_items) = entry.get("items").and_then(|i| i.as_array()) {
+                    let location = str_field(entry, &["name", "casket_name", "custom_name"]).unwrap_or(DEFAULT_LOCATION);
+                    items.extend( casket_items.iter().filter_map(|i| item_from_json(i, location)) );
+                } else {
+                    let location = str_field(entry, &["casket_name", "storage_unit", "location"]).unwrap_or(DEFAULT_LOCATION);
+                    items.extend( item_from_json(entry, location) );
+                }
+            }
+        },
+        Value::Object(obj) => {
+            for (casket, casket_items) in obj {
+                if let Some(arr) = casket_items.as_array() {
+                    items.extend( arr.iter().filter_map(|i| item_from_json(i, casket)) );
+                }
+            }
+        },
+        _ => return Err( format!("Storage unit file {} has to be a JSON array or object.", path.display()) )
+    }
+    Ok(items)
+}
+
+fn str_field<'a>(json: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter().find_map(|k| json.get(*k).and_then(|v| v.as_str())).filter(|s| !s.trim().is_empty())
+}
+
+/// Numbers are sometimes exported as strings
+fn num_field(json: &Value, keys: &[&str]) -> Option<f64> {
+    keys.iter().find_map(|k| {
+        let v = json.get(*k)?;
+        v.as_f64().or_else(|| v.as_str().and_then(|s| s.trim().parse::<f64>().ok()))
+    })
+}
+
+fn item_from_json(item: &Value, location: &str) -> Option<SteamData> {
+    let name = str_field(item, &["market_hash_name", "market_name", "name"])?;
+
+    Some( SteamData {
+        name: name.trim().to_string(),
+        quantity: Some( num_field(item, &["quantity", "amount", "count"]).map(|q| q as u16).unwrap_or(1) ),
+        asset_id: num_field(item, &["assetid", "asset_id", "id"]).map(|a| a as u64).unwrap_or(0),
+        float: num_field(item, &["paintwear", "float", "float_value"]),
+        pattern: num_field(item, &["paintseed", "pattern"]).map(|p| p as u32),
+        inspect_link: None,
+        location: Some(location.to_string()),
+        phase: None,
+        manual_note: None
+    } )
+}
+
+/// Adds the storage unit items to the items from the inventory.
+///
+/// When grouping, items with the same name are added to the same row and the locations are listed together.
+/// Otherwise every stored item is its own row, which needs its assetid, so the ones without are skipped. Returns how many were skipped.
+pub fn merge_storage_units(inventory: &mut Vec<SteamData>, stored: Vec<SteamData>, group_simular_items: bool) -> usize {
+    let mut skipped: usize = 0;
+
+    if !group_simular_items {
+        for item in stored {
+            if item.asset_id == 0 { skipped += 1; continue }
+            inventory.push( SteamData { quantity: None, ..item } );
+        }
+        return skipped
+    }
+
+    let mut index_by_name: HashMap<String, usize> = HashMap::new();
+    for (i, data) in inventory.iter().enumerate() { index_by_name.insert(data.name.clone(), i); }
+
+    for item in stored {
+        match index_by_name.get(&item.name) {
+            Some(&i) => {
+                let existing = &mut inventory[i];
+                existing.quantity = Some( existing.quantity.unwrap_or(1).saturating_add(item.quantity.unwrap_or(1)) );
+
+                let location = item.location.unwrap_or_else(|| DEFAULT_LOCATION.to_string());
+                let locations = existing.location.get_or_insert_with(|| String::from("Inventory"));
+                if !locations.split(", ").any(|l| l == location) {
+                    locations.push_str(", ");
+                    locations.push_str(&location);
+                }
+            },
+            None => {
+                index_by_name.insert(item.name.clone(), inventory.len());
+                inventory.push(item);
+            }
+        }
+    }
+    skipped
+}
@@ -0,0 +1,132 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use chrono::NaiveDateTime;
+
+/// How many backups of a spreadsheet are kept if not set in the savefile
+pub const DEFAULT_BACKUP_COUNT: u8 = 5;
+
+/// Backups are put in a `backups` folder next to the spreadsheet
+pub fn backup_dir(sheet_path: &Path) -> PathBuf {
+    sheet_path.parent()
+        .map(|p| p.join("backups"))
+        .unwrap_or_else(|| PathBuf::from("backups"))
+}
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+/// When `backup` was made, if it's named `{stem}_{timestamp}.{extension}` after `sheet_path`.
+/// The whole name has to match, so `portfolio_old_20260101_120000.xlsx` isn't a backup of `portfolio.xlsx`.
+fn backup_timestamp(sheet_path: &Path, backup: &Path) -> Option<NaiveDateTime> {
+    if backup.extension() != sheet_path.extension() { return None }
+
+    let stem = sheet_path.file_stem()?.to_string_lossy();
+    let backup_stem = backup.file_stem()?.to_string_lossy();
+    let timestamp = backup_stem.strip_prefix(stem.as_ref())?.strip_prefix('_')?;
+
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()
+}
+
+/// Every backup of `sheet_path`, oldest first
+pub fn list_backups(sheet_path: &Path) -> Vec<PathBuf> {
+    let Ok(dir) = fs::read_dir(backup_dir(sheet_path)) else { return Vec::new() };
+
+    let mut backups = dir
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .filter_map(|p| Some((backup_timestamp(sheet_path, &p)?, p)))
+        .collect::<Vec<(NaiveDateTime, PathBuf)>>();
+
+    backups.sort();
+    backups.into_iter().map(|(_, p)| p).collect()
+}
+
+/// Copies the spreadsheet into the backups folder and deletes the oldest backups so only `keep` are left.
+/// Returns None if there was nothing to back up (new spreadsheet) or backups are turned off with `keep` 0.
+pub fn backup_sheet(sheet_path: &Path, keep: u8) -> Result<Option<PathBuf>, String> {
+    if keep == 0 || !sheet_path.is_file() { return Ok(None) }
+
+    let dir = backup_dir(sheet_path);
+    fs::create_dir_all(&dir).map_err(|e| format!("Couldn't create the backup folder {}. \n{}", dir.display(), e))?;
+
+    let mut backup_name = format!(
+        "{}_{}",
+        sheet_path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default(),
+        chrono::Local::now().format(TIMESTAMP_FORMAT)
+    );
+    if let Some(extension) = sheet_path.extension() {
+        backup_name.push('.');
+        backup_name.push_str(&extension.to_string_lossy());
+    }
+
+    let backup_path = dir.join(backup_name);
+
+    fs::copy(sheet_path, &backup_path)
+        .map_err(|e| format!("Couldn't back up {} to {}. \n{}", sheet_path.display(), backup_path.display(), e))?;
+
+    let backups = list_backups(sheet_path);
+
+    if backups.len() > keep as usize {
+        for old in &backups[..backups.len() - keep as usize] {
+            fs::remove_file(old).map_err(|e| format!("Couldn't delete the old backup {}. \n{}", old.display(), e))?;
+        }
+    }
+    Ok(Some(backup_path))
+}
+
+/// Copies the newest backup over the spreadsheet. Returns the backup that was restored.
+pub fn restore_last_backup(sheet_path: &Path) -> Result<PathBuf, String> {
+    let last = list_backups(sheet_path)
+        .pop()
+        .ok_or_else(|| format!("There are no backups of {} in {}.", sheet_path.display(), backup_dir(sheet_path).display()))?;
+
+    fs::copy(&last, sheet_path)
+        .map_err(|e| format!("Couldn't restore {} to {}. Close Excel/LibreOffice/OpenOffice if it is open. \n{}", last.display(), sheet_path.display(), e))?;
+
+    Ok(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_exact_backup_names_match() {
+        let sheet = Path::new("/sheets/portfolio.xlsx");
+
+        assert!(backup_timestamp(sheet, Path::new("/sheets/backups/portfolio_20260101_120000.xlsx")).is_some());
+        assert!(backup_timestamp(sheet, Path::new("/sheets/backups/portfolio_old_20260101_120000.xlsx")).is_none());
+        assert!(backup_timestamp(sheet, Path::new("/sheets/backups/portfolio_20260101_120000.ods")).is_none());
+        assert!(backup_timestamp(sheet, Path::new("/sheets/backups/portfolio_20260101.xlsx")).is_none());
+        assert!(backup_timestamp(sheet, Path::new("/sheets/backups/portfolio20260101_120000.xlsx")).is_none());
+        assert!(backup_timestamp(sheet, Path::new("/sheets/backups/portfolio_20260101_120000_copy.xlsx")).is_none());
+    }
+
+    #[test]
+    fn backups_are_listed_oldest_first_without_other_workbooks() {
+        let dir = std::env::temp_dir().join(format!("cs2excel_backup_test_{}", std::process::id()));
+        let backups = dir.join("backups");
+        fs::create_dir_all(&backups).unwrap();
+
+        let sheet = dir.join("portfolio.xlsx");
+        for name in [
+            "portfolio_20260301_090000.xlsx",
+            "portfolio_20251231_235959.xlsx",
+            "portfolio_old_20270101_000000.xlsx",
+            "portfolio_20260101_120000.xlsx",
+        ] {
+            fs::write(backups.join(name), b"").unwrap();
+        }
+
+        let names = list_backups(&sheet).into_iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(names, [
+            "portfolio_20251231_235959.xlsx",
+            "portfolio_20260101_120000.xlsx",
+            "portfolio_20260301_090000.xlsx",
+        ]);
+    }
+}
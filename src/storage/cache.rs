@@ -0,0 +1,195 @@
+use std::{fs, path::{Path, PathBuf}, str::FromStr};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::models::web::{CachedMarket, Sites};
+
+/// Directory that the cached markets, exchange rates and inventories are saved to
+pub fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or(std::env::temp_dir())
+        .join("cs2excel")
+        .join("cache")
+}
+
+/// Path of the cached prices of `market` fetched by the price source called `source_name`
+pub fn market_cache_path(market: Sites, source_name: &str) -> PathBuf {
+    cache_dir().join( format!("{}_cache_{}.json", market.as_str(), source_name) )
+}
+
+/// What a file in the cache dir holds, read from its name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheKind {
+    Market { market: Sites, source: String },
+    Inventory { steamid: u64, context: u32 },
+    ExchangeRates,
+    Other
+}
+
+impl CacheKind {
+    fn from_file_name(name: &str) -> Self {
+        let Some(stem) = name.strip_suffix(".json") else { return CacheKind::Other };
+
+        if stem == "exchange_rates_cache" { return CacheKind::ExchangeRates }
+
+        if let Some(inv) = stem.strip_prefix("steam_inventory_")
+        && let Some((steamid, context)) = inv.split_once("_")
+        && let (Ok(steamid), Ok(context)) = (steamid.parse::<u64>(), context.parse::<u32>()) {
+            return CacheKind::Inventory { steamid, context }
+        }
+
+        if let Some((market, source)) = stem.split_once("_cache_")
+        && let Ok(market) = Sites::from_str(market) {
+            return CacheKind::Market { market, source: source.to_string() }
+        }
+
+        CacheKind::Other
+    }
+}
+
+#[derive(Debug)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    pub kind: CacheKind,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub size: u64
+}
+
+impl CacheEntry {
+    pub fn age(&self) -> Option<TimeDelta> {
+        self.timestamp.map(|t| Utc::now().signed_duration_since(t))
+    }
+}
+
+#[derive(Deserialize)]
+struct Timestamped {
+    timestamp: DateTime<Utc>
+}
+
+/// Every cached file sorted by name, with the time it was fetched and its size in bytes.
+pub fn list_cache() -> Result<Vec<CacheEntry>, String> {
+    let dir = cache_dir();
+    if !dir.exists() { return Ok(Vec::new()) }
+
+    let mut entries: Vec<CacheEntry> = fs::read_dir(&dir)
+        .map_err(|e| format!("Couldn't read cache dir {}. \n{}", dir.display(), e))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let metadata = entry.metadata().ok();
+
+            CacheEntry {
+                kind: CacheKind::from_file_name(&name),
+                timestamp: cache_timestamp(&path).or_else(|| metadata.as_ref().and_then(|m| m.modified().ok()).map(DateTime::<Utc>::from)),
+                size: metadata.map(|m| m.len()).unwrap_or(0),
+                path
+            }
+        })
+        .collect();
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Time the prices/inventory in the file were fetched. Per item caches keep this when items are added, unlike the modified time.
+fn cache_timestamp(path: &Path) -> Option<DateTime<Utc>> {
+    let file = fs::read(path).ok()?;
+    serde_json::from_slice::<Timestamped>(&file).ok().map(|t| t.timestamp)
+}
+
+/// The cached entry of `item_name` in every cached market, as it was saved by the price source.
+pub fn inspect_item(item_name: &str) -> Result<Vec<(Sites, String, Option<Value>)>, String> {
+    let mut found = Vec::new();
+
+    for entry in list_cache()? {
+        let CacheKind::Market { market, source } = entry.kind else { continue };
+
+        let file = fs::read(&entry.path).map_err(|e| format!("Read of {} failed! | {}", entry.path.display(), e))?;
+        let mut cached = serde_json::from_slice::<CachedMarket>(&file)
+            .map_err(|e| format!("Failed to deserialize {}! | {}", entry.path.display(), e))?;
+
+        let item = cached.prices.get_mut(item_name).map(Value::take);
+        found.push((market, source, item));
+    }
+    Ok(found)
+}
+
+/// What to purge from the cache dir.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PurgeFilter {
+    All,
+    Market(Sites),
+    OlderThan(TimeDelta)
+}
+
+impl FromStr for PurgeFilter {
+    type Err = String;
+
+    /// "all", a market name, or an age like "12h" or "7d"
+    fn from_str(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+
+        if s.eq_ignore_ascii_case("all") { return Ok(PurgeFilter::All) }
+        if let Ok(market) = Sites::from_str(s) { return Ok(PurgeFilter::Market(market)) }
+
+        let invalid = || format!("{} is not 'all', a market or an age like 12h/7d.", s);
+
+        // Split on the last char, not byte, so "5ä" is just invalid
+        let Some((unit_at, unit)) = s.char_indices().last() else { return Err(invalid()) };
+        let amount = s[..unit_at].parse::<u32>().map_err(|_| invalid())?;
+
+        match unit.to_ascii_lowercase() {
+            'h' => Ok(PurgeFilter::OlderThan(TimeDelta::hours(amount as i64))),
+            'd' => Ok(PurgeFilter::OlderThan(TimeDelta::days(amount as i64))),
+            _ => Err(invalid())
+        }
+    }
+}
+
+/// Deletes the cached files matching `filter` and returns their paths.
+pub fn purge_cache(filter: PurgeFilter) -> Result<Vec<PathBuf>, String> {
+    let mut purged = Vec::new();
+
+    for entry in list_cache()? {
+        let matches = match filter {
+            PurgeFilter::All => true,
+            PurgeFilter::Market(m) => matches!(&entry.kind, CacheKind::Market { market, .. } if *market == m),
+            PurgeFilter::OlderThan(max_age) => entry.age().is_some_and(|age| age > max_age)
+        };
+        if !matches { continue }
+
+        fs::remove_file(&entry.path).map_err(|e| format!("Couldn't delete {}. \n{}", entry.path.display(), e))?;
+        purged.push(entry.path);
+    }
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn purge_filter_all_and_markets() {
+        assert_eq!(PurgeFilter::from_str("all"), Ok(PurgeFilter::All));
+        assert_eq!(PurgeFilter::from_str(" ALL "), Ok(PurgeFilter::All));
+        assert_eq!(PurgeFilter::from_str("buff163"), Ok(PurgeFilter::Market(Sites::BUFF163)));
+    }
+
+    #[test]
+    fn purge_filter_ages() {
+        assert_eq!(PurgeFilter::from_str("12h"), Ok(PurgeFilter::OlderThan(TimeDelta::hours(12))));
+        assert_eq!(PurgeFilter::from_str("7D"), Ok(PurgeFilter::OlderThan(TimeDelta::days(7))));
+        assert_eq!(PurgeFilter::from_str("0d"), Ok(PurgeFilter::OlderThan(TimeDelta::zero())));
+    }
+
+    #[test]
+    fn purge_filter_invalid_is_err() {
+        for s in ["", "h", "d7", "-5d", "5m", "5ä", "ä", "12 h", "99999999999d"] {
+            assert!(PurgeFilter::from_str(s).is_err(), "{} should not parse", s);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use ahash::HashMap;
+use chrono::{Datelike, Days, NaiveDate};
+
+/// Every exchange_rates.json fetched gets saved here as `{YYYY-MM-DD}.json`, so buy prices can be converted
+/// with the rates from around when the item was bought. A later run on the same day overwrites that days snapshot.
+pub struct ExchangeRateHistory {
+    /// Oldest first
+    snapshots: Vec<(NaiveDate, HashMap<String, f64>)>
+}
+
+impl ExchangeRateHistory {
+    /// Path of the folder with the snapshots, inside the data dir of the user
+    pub fn default_dir() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or(std::env::temp_dir())
+            .join("cs2excel")
+            .join("exchange_rates")
+    }
+
+    /// Saves the rates fetched this run as the snapshot of `date`
+    pub fn save_snapshot(rates: &HashMap<String, f64>, date: NaiveDate) -> Result<PathBuf, String> {
+        let dir = Self::default_dir();
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Unable to create dir for exchange rate history at {}. \n{}", dir.display(), e))?;
+
+        let path = dir.join(format!("{}.json", date.format("%Y-%m-%d")));
+        let bytes = serde_json::to_vec(rates).map_err(|e| format!("Error serializing exchange rates | {}", e))?;
+
+        fs::write(&path, bytes).map_err(|e| format!("Unable to save exchange rates to {}. \n{}", path.display(), e))?;
+        Ok(path)
+    }
+
+    pub fn load_default() -> Result<Self, String> {
+        Self::load(&Self::default_dir())
+    }
+
+    /// Reads every snapshot in `dir`. Files that aren't named after a date or can't be read are skipped.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let Ok(entries) = fs::read_dir(dir) else { return Ok(ExchangeRateHistory { snapshots: Vec::new() }) };
+
+        let mut snapshots = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .filter_map(|p| {
+                let date = NaiveDate::parse_from_str(&p.file_stem()?.to_string_lossy(), "%Y-%m-%d").ok()?;
+                let rates = serde_json::from_slice::<HashMap<String, f64>>(&fs::read(&p).ok()?).ok()?;
+                Some((date, rates))
+            })
+            .collect::<Vec<(NaiveDate, HashMap<String, f64>)>>();
+
+        snapshots.sort_by_key(|(date, _)| *date);
+        Ok(ExchangeRateHistory { snapshots })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// USD to `currency` from the snapshot closest to `date`, the newest one if there's no date.
+    /// Only snapshots that have the currency are looked at. USD is always 1.0.
+    pub fn usd_to_x_closest_to(&self, currency: &str, date: Option<NaiveDate>) -> Option<f64> {
+        if currency.eq_ignore_ascii_case("USD") { return Some(1.0) }
+        let currency = currency.to_uppercase();
+
+        let mut with_currency = self.snapshots.iter()
+            .filter_map(|(d, rates)| rates.get(&currency).map(|r| (*d, *r)));
+
+        match date {
+            Some(date) => with_currency
+                .min_by_key(|(d, _)| (*d - date).num_days().abs())
+                .map(|(_, r)| r),
+            None => with_currency.next_back().map(|(_, r)| r)
+        }
+    }
+}
+
+/// Buy and sold dates are written by hand, so the usual formats are tried. A number is an excel date serial.
+pub fn parse_sheet_date(date: &str) -> Option<NaiveDate> {
+    // Only the date if there's a time after it
+    let date = date.split_whitespace().next()?;
+
+    for format in ["%Y-%m-%d", "%d/%m/%Y", "%d.%m.%Y", "%d-%m-%Y"] {
+        // %Y takes two digit years too, 03-04-26 isn't the year 26
+        if let Ok(d) = NaiveDate::parse_from_str(date, format) && d.year() >= 1900 { return Some(d) }
+    }
+
+    excel_serial_to_date(date.parse::<f64>().ok()?)
+}
+
+/// Excel counts days from 1899-12-30, the fraction is the time of day
+pub fn excel_serial_to_date(serial: f64) -> Option<NaiveDate> {
+    if !serial.is_finite() || serial < 1.0 { return None }
+    NaiveDate::from_ymd_opt(1899, 12, 30)?.checked_add_days(Days::new(serial as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(y, m, d)
+    }
+
+    #[test]
+    fn parses_written_dates() {
+        assert_eq!(parse_sheet_date("2026-03-04"), date(2026, 3, 4));
+        assert_eq!(parse_sheet_date("04/03/2026"), date(2026, 3, 4));
+        assert_eq!(parse_sheet_date("04.03.2026"), date(2026, 3, 4));
+        assert_eq!(parse_sheet_date("04-03-2026"), date(2026, 3, 4));
+        assert_eq!(parse_sheet_date("2026-03-04 13:37:00"), date(2026, 3, 4));
+    }
+
+    #[test]
+    fn parses_excel_serials() {
+        assert_eq!(parse_sheet_date("46085"), date(2026, 3, 4));
+        assert_eq!(parse_sheet_date("46085.75"), date(2026, 3, 4));
+        assert_eq!(excel_serial_to_date(1.0), date(1899, 12, 31));
+        assert_eq!(excel_serial_to_date(0.0), None);
+        assert_eq!(excel_serial_to_date(f64::NAN), None);
+    }
+
+    #[test]
+    fn rejects_two_digit_years_and_garbage() {
+        assert_eq!(parse_sheet_date("03-04-26"), None);
+        assert_eq!(parse_sheet_date("3/4/26"), None);
+        assert_eq!(parse_sheet_date(""), None);
+        assert_eq!(parse_sheet_date("last tuesday"), None);
+        assert_eq!(parse_sheet_date("-5"), None);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod backup;
+pub mod cache;
+pub mod exchange_history;
+pub mod price_history;
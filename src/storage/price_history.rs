@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+use crate::models::excel::ExcelData;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id        INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp TEXT    NOT NULL,
+        steamid   INTEGER NOT NULL,
+        currency  TEXT    NOT NULL,
+        usd_to_x  REAL    NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS prices (
+        run_id      INTEGER NOT NULL REFERENCES runs(id),
+        timestamp   TEXT    NOT NULL,
+        market_name TEXT    NOT NULL,
+        phase       TEXT,
+        market      TEXT,
+        price_usd   REAL    NOT NULL,
+        price_x     REAL    NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS prices_market_name ON prices (market_name, timestamp);
+";
+
+/// Local SQLite database that every priced item gets appended to, one snapshot per run.
+///
+/// Timestamps are stored as RFC 3339 in UTC so they sort and compare as text.
+pub struct PriceHistoryDb {
+    db: Connection
+}
+
+impl PriceHistoryDb {
+    /// Path of the database, inside the data dir of the user
+    pub fn default_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or(std::env::temp_dir())
+            .join("cs2excel")
+            .join("price_history.sqlite")
+    }
+
+    pub fn open_default() -> Result<Self, String> {
+        Self::open(&Self::default_path())
+    }
+
+    /// Opens (or creates) the database at `path` and makes sure the tables exist.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Unable to create dir for price history at {}. \n{}", parent.display(), e))?;
+        }
+
+        let db = Connection::open(path)
+            .map_err(|e| format!("Unable to open price history DB at {}. \n{}", path.display(), e))?;
+
+        db.execute_batch(SCHEMA)
+            .map_err(|e| format!("Unable to create price history tables. \n{}", e))?;
+
+        Ok( PriceHistoryDb{ db } )
+    }
+
+    /// Saves one snapshot of every item in `exceldata` that got a price this run. `rate` is the `usd_to_x` rate
+    /// the prices were converted with, so the USD price is `price / rate`.
+    ///
+    /// Returns the amount of prices saved.
+    pub fn insert_run(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        steamid: u64,
+        currency: &str,
+        rate: f64,
+        exceldata: &[ExcelData]
+    ) -> Result<usize, String> {
+        let timestamp = timestamp.to_rfc3339();
+
+        let tx = self.db.transaction()
+            .map_err(|e| format!("Unable to start price history transaction. \n{}", e))?;
+
+        tx.execute(
+            "INSERT INTO runs (timestamp, steamid, currency, usd_to_x) VALUES (?1, ?2, ?3, ?4)",
+            params![timestamp, steamid as i64, currency, rate]
+        ).map_err(|e| format!("Unable to insert run into price history. \n{}", e))?;
+
+        let run_id = tx.last_insert_rowid();
+        let mut saved: usize = 0;
+
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO prices (run_id, timestamp, market_name, phase, market, price_usd, price_x)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+            ).map_err(|e| format!("Unable to prepare price history insert. \n{}", e))?;
+
+            for data in exceldata {
+                let Some(price) = data.price else { continue };
+                let price_usd = if rate != 0.0 { price / rate } else { price };
+
+                stmt.execute(
+                    params![run_id, timestamp, data.name, data.phase, data.market, price_usd, price]
+                ).map_err(|e| format!("Unable to insert price of {} into price history. \n{}", data.name, e))?;
+
+                saved += 1;
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Unable to commit price history. \n{}", e))?;
+
+        Ok(saved)
+    }
+}
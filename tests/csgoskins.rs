@@ -0,0 +1,142 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    thread
+};
+
+use serde_json::Value;
+
+use cs2excel::{
+    browser::{csgoskins::CsgoskinsSource, price_source::PriceSource},
+    excel::helpers::{Progress, ProgressSink},
+    models::{price::{Doppler, PriceType}, web::Sites},
+    parsing::csgoskins_url::create_csgoskins_urls
+};
+
+const OFFERS: &str = "AK-47 | Redline (Field-Tested)";
+const OFFERS_MARKUP: &str = "M4A4 | Asiimov (Field-Tested)";
+const NO_OFFERS: &str = "Sticker | Sneaky Beaky Like";
+const MALFORMED: &str = "AWP | Dragon Lore (Factory New)";
+const RATE_LIMITED: &str = "Glock-18 | Fade (Factory New)";
+const NO_PAGE: &str = "Desert Eagle | Blaze (Factory New)";
+
+struct Silent;
+
+impl ProgressSink for Silent {
+    async fn send(&mut self, _: Progress) { }
+    async fn send_str(&mut self, _: &str) { }
+}
+
+/// Stand-in for csgoskins.gg, serving the fixture pages on a local port. Returns the base url of it.
+fn serve_fixtures() -> String {
+    let pages: Vec<(String, u16, &'static str)> = vec![
+        (item_path(OFFERS), 200, include_str!("fixtures/csgoskins/offers.html")),
+        (item_path(OFFERS_MARKUP), 200, include_str!("fixtures/csgoskins/offers_markup.html")),
+        (item_path(NO_OFFERS), 200, include_str!("fixtures/csgoskins/no_offers.html")),
+        (item_path(MALFORMED), 200, include_str!("fixtures/csgoskins/malformed.html")),
+        (item_path(RATE_LIMITED), 429, "Too Many Requests"),
+    ];
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            respond(stream, &pages);
+        }
+    });
+    base_url
+}
+
+fn item_path(item_name: &str) -> String {
+    format!("/items/{}", create_csgoskins_urls(item_name))
+}
+
+fn respond(mut stream: TcpStream, pages: &[(String, u16, &'static str)]) {
+    let mut request_line = String::new();
+    {
+        let mut reader = BufReader::new(&stream);
+        if reader.read_line(&mut request_line).is_err() { return }
+
+        // Rest of the headers, the requests are GETs so there's no body
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) { line.clear(); }
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = pages.iter()
+        .find(|(p, _, _)| p == path)
+        .map(|(_, status, body)| (*status, *body))
+        .unwrap_or((404, "Not Found"));
+
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} Fixture\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+}
+
+async fn snapshot(source: &CsgoskinsSource, market: Sites, item_names: &[&str]) -> Value {
+    source.get_market_data(market, item_names, &mut Silent).await.unwrap()
+}
+
+#[tokio::test]
+async fn offers_are_the_cheapest_usd_price_per_market() {
+    let source = CsgoskinsSource::with_base_url(&serve_fixtures(), 0);
+
+    assert_eq!(snapshot(&source, Sites::CSFLOAT, &[OFFERS]).await[OFFERS], Value::from(10.5));
+    assert_eq!(snapshot(&source, Sites::BUFF163, &[OFFERS]).await[OFFERS], Value::from(9.8));
+    assert_eq!(snapshot(&source, Sites::STEAM, &[OFFERS]).await[OFFERS], Value::from(1234.56));
+    // Only offer is in CNY
+    assert_eq!(snapshot(&source, Sites::YOUPIN, &[OFFERS]).await[OFFERS], Value::Null);
+}
+
+#[tokio::test]
+async fn offers_are_read_from_the_markup_without_json_ld() {
+    let source = CsgoskinsSource::with_base_url(&serve_fixtures(), 0);
+
+    assert_eq!(snapshot(&source, Sites::SKINPORT, &[OFFERS_MARKUP]).await[OFFERS_MARKUP], Value::from(123.45));
+    assert_eq!(snapshot(&source, Sites::BITSKINS, &[OFFERS_MARKUP]).await[OFFERS_MARKUP], Value::from(120.0));
+    assert_eq!(snapshot(&source, Sites::STEAM, &[OFFERS_MARKUP]).await[OFFERS_MARKUP], Value::Null);
+    // Listed without a price, the one on the next row isn't its price
+    assert_eq!(snapshot(&source, Sites::CSFLOAT, &[OFFERS_MARKUP]).await[OFFERS_MARKUP], Value::Null);
+}
+
+#[tokio::test]
+async fn no_offers_and_no_page_are_null() {
+    let source = CsgoskinsSource::with_base_url(&serve_fixtures(), 0);
+    let prices = snapshot(&source, Sites::CSFLOAT, &[NO_OFFERS, NO_PAGE]).await;
+
+    assert_eq!(prices.get(NO_OFFERS), Some(&Value::Null));
+    assert_eq!(prices.get(NO_PAGE), Some(&Value::Null));
+}
+
+#[tokio::test]
+async fn malformed_page_has_no_offers() {
+    let source = CsgoskinsSource::with_base_url(&serve_fixtures(), 0);
+    let prices = snapshot(&source, Sites::CSFLOAT, &[MALFORMED]).await;
+
+    assert_eq!(prices.get(MALFORMED), Some(&Value::Null));
+}
+
+#[tokio::test]
+async fn failed_page_is_skipped_without_losing_the_rest() {
+    let source = CsgoskinsSource::with_base_url(&serve_fixtures(), 0);
+    let prices = snapshot(&source, Sites::CSFLOAT, &[OFFERS, RATE_LIMITED, NO_OFFERS]).await;
+
+    assert_eq!(prices.get(OFFERS), Some(&Value::from(10.5)));
+    assert_eq!(prices.get(NO_OFFERS), Some(&Value::Null));
+    // Left out so it's fetched again the next time
+    assert_eq!(prices.get(RATE_LIMITED), None);
+}
+
+#[tokio::test]
+async fn doppler_phases_have_no_price() {
+    let source = CsgoskinsSource::with_base_url(&serve_fixtures(), 0);
+    let prices = snapshot(&source, Sites::CSFLOAT, &[OFFERS]).await;
+
+    assert_eq!(source.get_price(OFFERS, &prices, Sites::CSFLOAT, PriceType::StartingAt, &None, &mut Silent).await, Ok(Some(10.5)));
+    assert_eq!(source.get_price(OFFERS, &prices, Sites::CSFLOAT, PriceType::StartingAt, &Some(Doppler::Ruby), &mut Silent).await, Ok(None));
+}